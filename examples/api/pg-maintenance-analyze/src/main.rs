@@ -5,17 +5,81 @@ use std::process::ExitCode;
 
 use tokio::net::TcpListener;
 
+use axum::http::HeaderMap;
+
 use async_graphql_axum::GraphQLRequest;
 use async_graphql_axum::GraphQLResponse;
+use async_graphql_axum::GraphQLSubscription;
 
 use rs_pg_maintenance_analyze::PgSchema;
+use rs_pg_maintenance_analyze::config::MaintenanceConfig;
+use rs_pg_maintenance_analyze::history::Actor;
+use rs_pg_maintenance_analyze::history::HistoryConfig;
+use rs_pg_maintenance_analyze::job::JobRetention;
+use rs_pg_maintenance_analyze::retry::RetryPolicy;
+use rs_pg_maintenance_analyze::window::MaintenanceWindow;
+use rs_pg_maintenance_analyze::window::WindowPolicy;
+
+/// Creates the config from the environment.
+///
+/// - HISTORY_TABLE: e.g, maint.history
+/// - HISTORY_ACTOR: e.g, ops-team(recorded unless the request has the actor header)
+/// - MAINTENANCE_WINDOW: e.g, "22:00-06:00 Asia/Tokyo"(all operations)
+/// - RETRY_MAX_ATTEMPTS: e.g, 3(not retried if not specified)
+/// - JOB_RETENTION_MAX_FINISHED: e.g, 100(the finished jobs kept in memory)
+fn env2config() -> Result<MaintenanceConfig, io::Error> {
+    let history: Option<HistoryConfig> = match env::var("HISTORY_TABLE") {
+        Ok(table) => Some(HistoryConfig {
+            actor: env::var("HISTORY_ACTOR").ok(),
+            ..HistoryConfig::parse(&table)?
+        }),
+        Err(_) => None,
+    };
+    let windows: Vec<MaintenanceWindow> = match env::var("MAINTENANCE_WINDOW") {
+        Ok(spec) => vec![MaintenanceWindow::parse(vec![], &spec)?],
+        Err(_) => vec![],
+    };
+    let max_attempts: u32 = match env::var("RETRY_MAX_ATTEMPTS") {
+        Ok(n) => n.parse().map_err(io::Error::other)?,
+        Err(_) => RetryPolicy::default().max_attempts,
+    };
+    let max_finished: usize = match env::var("JOB_RETENTION_MAX_FINISHED") {
+        Ok(n) => n.parse().map_err(io::Error::other)?,
+        Err(_) => JobRetention::default().max_finished,
+    };
+    Ok(MaintenanceConfig {
+        history,
+        job_retention: JobRetention {
+            max_finished,
+            ..Default::default()
+        },
+        windows: WindowPolicy {
+            windows,
+            ..Default::default()
+        },
+        retry: RetryPolicy {
+            max_attempts,
+            ..Default::default()
+        },
+        ..Default::default()
+    })
+}
 
 async fn conn2schema(conn_str: &str) -> Result<PgSchema, io::Error> {
-    rs_pg_maintenance_analyze::conn2schema(conn_str).await
+    let cfg: MaintenanceConfig = env2config()?;
+    rs_pg_maintenance_analyze::conn2schema_with_config(conn_str, &cfg).await
 }
 
-async fn req2res(s: &PgSchema, req: GraphQLRequest) -> GraphQLResponse {
-    s.execute(req.into_inner()).await.into()
+/// Executes the request as the caller in the X-Maintenance-Actor header(e.g, set by a proxy).
+async fn req2res(s: &PgSchema, headers: HeaderMap, req: GraphQLRequest) -> GraphQLResponse {
+    let actor: Option<&str> = headers
+        .get("x-maintenance-actor")
+        .and_then(|v| v.to_str().ok());
+    let req = match actor {
+        Some(actor) => req.into_inner().data(Actor(actor.into())),
+        None => req.into_inner(),
+    };
+    s.execute(req).await.into()
 }
 
 async fn sub() -> Result<(), io::Error> {
@@ -30,10 +94,14 @@ async fn sub() -> Result<(), io::Error> {
 
     let listener = TcpListener::bind(listen_addr).await?;
 
-    let app = axum::Router::new().route(
-        "/",
-        axum::routing::post(|req: GraphQLRequest| async move { req2res(&s, req).await }),
-    );
+    let app = axum::Router::new()
+        .route_service("/ws", GraphQLSubscription::new(s.clone()))
+        .route(
+            "/",
+            axum::routing::post(|headers: HeaderMap, req: GraphQLRequest| async move {
+                req2res(&s, headers, req).await
+            }),
+        );
 
     axum::serve(listener, app).await
 }
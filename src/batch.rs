@@ -0,0 +1,189 @@
+use std::io;
+use std::time::Duration;
+
+use chrono::DateTime;
+use chrono::Utc;
+
+use async_graphql::Enum;
+use async_graphql::SimpleObject;
+
+use crate::error::MaintenanceError;
+use crate::locks::Blocker;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Enum)]
+pub enum MaintenanceOperation {
+    Analyze,
+    Vacuum,
+    Reindex,
+    RefreshMaterializedView,
+}
+
+impl MaintenanceOperation {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Analyze => "ANALYZE",
+            Self::Vacuum => "VACUUM",
+            Self::Reindex => "REINDEX",
+            Self::RefreshMaterializedView => "REFRESH MATERIALIZED VIEW",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Enum)]
+pub enum MaintenanceStatus {
+    Succeeded,
+    Failed,
+
+    /// Not processed(e.g, an earlier table failed).
+    Skipped,
+
+    /// Not processed: would be processed without the dry run.
+    Planned,
+}
+
+impl MaintenanceStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Succeeded => "SUCCEEDED",
+            Self::Failed => "FAILED",
+            Self::Skipped => "SKIPPED",
+            Self::Planned => "PLANNED",
+        }
+    }
+
+    /// true if the statement was executed(succeeded or failed).
+    pub fn is_executed(&self) -> bool {
+        matches!(self, Self::Succeeded | Self::Failed)
+    }
+}
+
+/// The relation name of the statements targeting the whole schema.
+pub const SCHEMA_WIDE: &str = "*";
+
+/// The result of a maintenance statement on a table(or an index).
+#[derive(Clone, SimpleObject)]
+pub struct MaintenanceResult {
+    pub schema: String,
+
+    /// The relation name("*" if the statement targets the whole schema).
+    pub table: String,
+    pub status: MaintenanceStatus,
+
+    /// The failure(or the reason why the relation was skipped).
+    pub error: Option<String>,
+
+    /// The code of the failure(e.g, LOCK_TIMEOUT).
+    pub error_code: Option<String>,
+    pub duration_ms: Option<i64>,
+    pub started_at: Option<DateTime<Utc>>,
+
+    /// The statement which was(or would be on a dry run) executed.
+    pub sql: Option<String>,
+
+    /// The estimated total size of the relation in bytes(including indexes and toast).
+    pub estimated_bytes: Option<i64>,
+
+    /// The executions of the statement(more than 1 if retried).
+    pub attempts: Option<i32>,
+
+    /// The problems which did not fail the statement(e.g, history not recorded).
+    pub warnings: Vec<String>,
+}
+
+impl MaintenanceResult {
+    pub fn new(
+        schema: String,
+        table: String,
+        started_at: DateTime<Utc>,
+        elapsed: Duration,
+        res: Result<String, io::Error>,
+    ) -> Self {
+        let (status, error, sql) = match res {
+            Ok(sql) => (MaintenanceStatus::Succeeded, None, Some(sql)),
+            Err(e) => (MaintenanceStatus::Failed, Some(e), None),
+        };
+        Self {
+            schema,
+            table,
+            status,
+            error_code: error
+                .as_ref()
+                .map(|e| MaintenanceError::from_io_ref(e).code().into()),
+            error: error.map(|e| e.to_string()),
+            duration_ms: Some(elapsed.as_millis() as i64),
+            started_at: Some(started_at),
+            sql,
+            estimated_bytes: None,
+            attempts: None,
+            warnings: vec![],
+        }
+    }
+
+    pub fn skipped(schema: String, table: String) -> Self {
+        Self {
+            schema,
+            table,
+            status: MaintenanceStatus::Skipped,
+            error: None,
+            error_code: None,
+            duration_ms: None,
+            started_at: None,
+            sql: None,
+            estimated_bytes: None,
+            attempts: None,
+            warnings: vec![],
+        }
+    }
+
+    /// Creates the result of a relation skipped for the reason(e.g, the window closed).
+    pub fn skipped_because(schema: String, table: String, reason: String) -> Self {
+        Self {
+            error: Some(reason),
+            ..Self::skipped(schema, table)
+        }
+    }
+
+    /// Creates the result of a relation skipped for the conflicting locks.
+    pub fn locked(schema: String, table: String, blockers: Vec<Blocker>) -> Self {
+        let e = MaintenanceError::LockConflict {
+            relation: format!("{schema}.{table}"),
+            blockers,
+        };
+        Self {
+            error_code: Some(e.code().into()),
+            ..Self::skipped_because(schema, table, e.to_string())
+        }
+    }
+
+    /// Creates the result of a dry run(the validation result if failed).
+    pub fn planned(schema: String, table: String, res: Result<String, io::Error>) -> Self {
+        match res {
+            Ok(sql) => Self {
+                status: MaintenanceStatus::Planned,
+                sql: Some(sql),
+                ..Self::skipped(schema, table)
+            },
+            Err(e) => Self {
+                status: MaintenanceStatus::Failed,
+                error: Some(e.to_string()),
+                error_code: Some(MaintenanceError::from_io_ref(&e).code().into()),
+                ..Self::skipped(schema, table)
+            },
+        }
+    }
+
+    pub fn with_estimated_bytes(self, estimated_bytes: Option<i64>) -> Self {
+        Self {
+            estimated_bytes,
+            ..self
+        }
+    }
+
+    /// Sets the executions of the statement(0: not executed).
+    pub fn with_attempts(self, attempts: i32) -> Self {
+        Self {
+            attempts: (0 < attempts).then_some(attempts),
+            ..self
+        }
+    }
+}
@@ -0,0 +1,51 @@
+use crate::history::HistoryConfig;
+use crate::job::JOB_WORKERS_DEFAULT;
+use crate::job::JobRetention;
+use crate::policy::TablePolicy;
+use crate::retry::RetryPolicy;
+use crate::schedule::ScheduleInput;
+use crate::timeout::TimeoutPolicy;
+use crate::window::WindowPolicy;
+
+/// The optional features of the maintenance service.
+#[derive(Clone)]
+pub struct MaintenanceConfig {
+    /// Records the executed maintenance if specified.
+    pub history: Option<HistoryConfig>,
+
+    /// The number of the in-process job workers.
+    pub job_workers: usize,
+
+    /// The finished jobs kept in memory.
+    pub job_retention: JobRetention,
+
+    /// The schedules created on start.
+    pub schedules: Vec<ScheduleInput>,
+
+    /// The operations are unrestricted if no window is specified.
+    pub windows: WindowPolicy,
+
+    /// The relations which can be maintained(system schemas denied by default).
+    pub policy: TablePolicy,
+
+    /// The timeouts used unless the caller specifies them.
+    pub timeouts: TimeoutPolicy,
+
+    /// Retries the statements failed by the transient failures(not retried by default).
+    pub retry: RetryPolicy,
+}
+
+impl Default for MaintenanceConfig {
+    fn default() -> Self {
+        Self {
+            history: None,
+            job_workers: JOB_WORKERS_DEFAULT,
+            job_retention: JobRetention::default(),
+            schedules: vec![],
+            windows: WindowPolicy::default(),
+            policy: TablePolicy::default(),
+            timeouts: TimeoutPolicy::default(),
+            retry: RetryPolicy::default(),
+        }
+    }
+}
@@ -0,0 +1,368 @@
+use std::fmt;
+use std::io;
+
+use async_graphql::ErrorExtensionValues;
+use async_graphql::ErrorExtensions;
+use async_graphql::Response;
+use async_graphql::ServerError;
+use async_graphql::extensions::Extension;
+use async_graphql::extensions::ExtensionContext;
+use async_graphql::extensions::ExtensionFactory;
+use async_graphql::extensions::NextRequest;
+
+use crate::locks::Blocker;
+use crate::locks::describe;
+
+/// An error returned by the database.
+#[derive(Debug, Clone)]
+pub struct DbError {
+    pub sqlstate: String,
+    pub message: String,
+}
+
+impl DbError {
+    /// Maps the SQLSTATE to the machine-readable code.
+    ///
+    /// 57014 is shared by the statement timeout and the cancel request: only the message tells
+    /// them apart, so a timeout is reported as CANCELED unless lc_messages is English.
+    pub fn code(&self) -> &'static str {
+        let state: &str = &self.sqlstate;
+        match state {
+            "42501" => "PERMISSION_DENIED",
+            "55P03" => "LOCK_TIMEOUT",
+            "57014" if self.message.contains("statement timeout") => "STATEMENT_TIMEOUT",
+            "57014" => "CANCELED",
+            "40001" => "SERIALIZATION_FAILURE",
+            "40P01" => "DEADLOCK_DETECTED",
+            "42P01" | "42703" | "42704" | "3F000" => "NOT_FOUND",
+            "57P01" | "57P02" | "57P03" => "CONNECTION_FAILED",
+            _ if state.starts_with("08") => "CONNECTION_FAILED",
+            _ => "DATABASE_ERROR",
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub enum MaintenanceError {
+    /// The relation(or the column, the index) does not exist.
+    NotFound(String),
+
+    /// The relation exists but is rejected by the policy.
+    Forbidden(String),
+
+    /// e.g, a view instead of a table, an invalid option.
+    InvalidInput(String),
+
+    /// Not supported by the server version.
+    Unsupported(String),
+
+    /// The errors of the names in a batch.
+    Batch(Vec<MaintenanceError>),
+
+    /// The pool could not get a connection(or the connection was lost).
+    Connection(String),
+
+    /// The operation is not allowed outside its maintenance windows.
+    OutsideWindow(String),
+
+    /// Other sessions lock the relation in a conflicting mode.
+    LockConflict {
+        relation: String,
+        blockers: Vec<Blocker>,
+    },
+
+    Database(DbError),
+
+    Other(String),
+}
+
+impl MaintenanceError {
+    pub fn code(&self) -> &'static str {
+        match self {
+            Self::NotFound(_) => "NOT_FOUND",
+            Self::Forbidden(_) => "FORBIDDEN",
+            Self::InvalidInput(_) => "INVALID_INPUT",
+            Self::Unsupported(_) => "UNSUPPORTED",
+            Self::Batch(errors) => {
+                let first: &str = errors.first().map(|e| e.code()).unwrap_or("INVALID_INPUT");
+                match errors.iter().all(|e| e.code() == first) {
+                    true => first,
+                    false => "INVALID_BATCH",
+                }
+            }
+            Self::Connection(_) => "CONNECTION_FAILED",
+            Self::OutsideWindow(_) => "OUTSIDE_WINDOW",
+            Self::LockConflict { .. } => "LOCK_CONFLICT",
+            Self::Database(d) => d.code(),
+            Self::Other(_) => "INTERNAL",
+        }
+    }
+
+    pub fn sqlstate(&self) -> Option<&str> {
+        match self {
+            Self::Database(d) => Some(&d.sqlstate),
+            _ => None,
+        }
+    }
+
+    /// Sets the code(and the SQLSTATE) to the extensions of a GraphQL error.
+    pub fn set_extensions(&self, ext: &mut ErrorExtensionValues) {
+        ext.set("code", self.code());
+        if let Some(sqlstate) = self.sqlstate() {
+            ext.set("sqlstate", sqlstate);
+        }
+        if let Self::LockConflict { blockers, .. } = self {
+            // the prepared transactions have no pid
+            let pids: Vec<i32> = blockers.iter().filter_map(|b| b.pid).collect();
+            ext.set("blockingPids", pids);
+        }
+    }
+
+    pub fn from_sqlx_ref(e: &sqlx::Error) -> Self {
+        match e {
+            sqlx::Error::Database(db) => match db.code() {
+                Some(sqlstate) => Self::Database(DbError {
+                    sqlstate: sqlstate.into(),
+                    message: db.message().into(),
+                }),
+                None => Self::Other(db.message().into()),
+            },
+            sqlx::Error::PoolTimedOut
+            | sqlx::Error::PoolClosed
+            | sqlx::Error::WorkerCrashed
+            | sqlx::Error::Io(_)
+            | sqlx::Error::Tls(_) => Self::Connection(e.to_string()),
+            _ => Self::Other(e.to_string()),
+        }
+    }
+
+    /// Recovers the error wrapped in the io::Error(e.g, by the executors).
+    pub fn from_io_ref(e: &io::Error) -> Self {
+        if let Some(inner) = e.get_ref() {
+            if let Some(m) = inner.downcast_ref::<Self>() {
+                return m.clone();
+            }
+            if let Some(s) = inner.downcast_ref::<sqlx::Error>() {
+                return Self::from_sqlx_ref(s);
+            }
+        }
+        match e.kind() {
+            io::ErrorKind::PermissionDenied => Self::Forbidden(e.to_string()),
+            _ => Self::Other(e.to_string()),
+        }
+    }
+}
+
+impl fmt::Display for MaintenanceError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::NotFound(m)
+            | Self::Forbidden(m)
+            | Self::InvalidInput(m)
+            | Self::Unsupported(m)
+            | Self::Connection(m)
+            | Self::OutsideWindow(m)
+            | Self::Other(m) => f.write_str(m),
+            Self::Database(d) => write!(f, "{} (SQLSTATE {})", d.message, d.sqlstate),
+            Self::LockConflict { relation, blockers } => {
+                write!(f, "{relation} is locked by {}", describe(blockers))
+            }
+            Self::Batch(errors) => {
+                let messages: Vec<String> = errors.iter().map(|e| e.to_string()).collect();
+                write!(
+                    f,
+                    "{} invalid name(s): {}",
+                    errors.len(),
+                    messages.join("; ")
+                )
+            }
+        }
+    }
+}
+
+impl std::error::Error for MaintenanceError {}
+
+impl From<sqlx::Error> for MaintenanceError {
+    fn from(e: sqlx::Error) -> Self {
+        Self::from_sqlx_ref(&e)
+    }
+}
+
+impl From<io::Error> for MaintenanceError {
+    fn from(e: io::Error) -> Self {
+        Self::from_io_ref(&e)
+    }
+}
+
+impl From<MaintenanceError> for io::Error {
+    /// Keeps the error to be recovered by MaintenanceError::from_io_ref.
+    fn from(e: MaintenanceError) -> Self {
+        let kind: io::ErrorKind = match e {
+            MaintenanceError::NotFound(_) => io::ErrorKind::NotFound,
+            MaintenanceError::Forbidden(_) => io::ErrorKind::PermissionDenied,
+            MaintenanceError::InvalidInput(_) => io::ErrorKind::InvalidInput,
+            MaintenanceError::Unsupported(_) => io::ErrorKind::Unsupported,
+            _ => io::ErrorKind::Other,
+        };
+        io::Error::new(kind, e)
+    }
+}
+
+impl ErrorExtensions for MaintenanceError {
+    fn extend(&self) -> async_graphql::Error {
+        async_graphql::Error::new(self.to_string()).extend_with(|_, ext| self.set_extensions(ext))
+    }
+}
+
+/// Adds the code(and the SQLSTATE) to the errors of the resolvers.
+fn add_code(e: &mut ServerError) {
+    let coded: bool = e
+        .extensions
+        .as_ref()
+        .is_some_and(|x| x.get("code").is_some());
+    let Some(source) = e.source.as_ref().filter(|_| !coded) else {
+        return;
+    };
+    let err: MaintenanceError = if let Some(io) = source.downcast_ref::<io::Error>() {
+        MaintenanceError::from_io_ref(io)
+    } else if let Some(m) = source.downcast_ref::<MaintenanceError>() {
+        m.clone()
+    } else if let Some(s) = source.downcast_ref::<sqlx::Error>() {
+        MaintenanceError::from_sqlx_ref(s)
+    } else {
+        return;
+    };
+    err.set_extensions(e.extensions.get_or_insert_with(Default::default));
+}
+
+/// The schema extension which adds the error codes to the responses.
+pub struct ErrorCodes;
+
+impl ExtensionFactory for ErrorCodes {
+    fn create(&self) -> std::sync::Arc<dyn Extension> {
+        std::sync::Arc::new(ErrorCodesExtension)
+    }
+}
+
+struct ErrorCodesExtension;
+
+#[async_trait::async_trait]
+impl Extension for ErrorCodesExtension {
+    async fn request(&self, ctx: &ExtensionContext<'_>, next: NextRequest<'_>) -> Response {
+        let mut resp: Response = next.run(ctx).await;
+        resp.errors.iter_mut().for_each(add_code);
+        resp
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn db(sqlstate: &str, message: &str) -> DbError {
+        DbError {
+            sqlstate: sqlstate.into(),
+            message: message.into(),
+        }
+    }
+
+    #[test]
+    fn sqlstate_codes() {
+        for (sqlstate, code) in [
+            ("42501", "PERMISSION_DENIED"),
+            ("55P03", "LOCK_TIMEOUT"),
+            ("40001", "SERIALIZATION_FAILURE"),
+            ("40P01", "DEADLOCK_DETECTED"),
+            ("42P01", "NOT_FOUND"),
+            ("42703", "NOT_FOUND"),
+            ("42704", "NOT_FOUND"),
+            ("3F000", "NOT_FOUND"),
+            ("57P01", "CONNECTION_FAILED"),
+            ("57P02", "CONNECTION_FAILED"),
+            ("57P03", "CONNECTION_FAILED"),
+            ("08006", "CONNECTION_FAILED"),
+            ("08P01", "CONNECTION_FAILED"),
+            ("23505", "DATABASE_ERROR"),
+            ("", "DATABASE_ERROR"),
+        ] {
+            assert_eq!(db(sqlstate, "failed").code(), code, "{sqlstate}");
+        }
+    }
+
+    #[test]
+    fn canceled_or_statement_timeout() {
+        let timeout = db("57014", "canceling statement due to statement timeout");
+        assert_eq!(timeout.code(), "STATEMENT_TIMEOUT");
+        let canceled = db("57014", "canceling statement due to user request");
+        assert_eq!(canceled.code(), "CANCELED");
+        // the localized message can not be told apart from the cancel request
+        let localized = db("57014", "Abbruch der Anweisung wegen Zeitüberschreitung");
+        assert_eq!(localized.code(), "CANCELED");
+    }
+
+    #[test]
+    fn io_round_trip() {
+        for (err, kind) in [
+            (
+                MaintenanceError::NotFound("no table".into()),
+                io::ErrorKind::NotFound,
+            ),
+            (
+                MaintenanceError::Forbidden("denied".into()),
+                io::ErrorKind::PermissionDenied,
+            ),
+            (
+                MaintenanceError::InvalidInput("bad".into()),
+                io::ErrorKind::InvalidInput,
+            ),
+            (
+                MaintenanceError::Unsupported("old".into()),
+                io::ErrorKind::Unsupported,
+            ),
+            (
+                MaintenanceError::OutsideWindow("closed".into()),
+                io::ErrorKind::Other,
+            ),
+            (
+                MaintenanceError::Database(db("55P03", "could not obtain lock")),
+                io::ErrorKind::Other,
+            ),
+        ] {
+            let code: &str = err.code();
+            let message: String = err.to_string();
+            let io_err: io::Error = err.into();
+            assert_eq!(io_err.kind(), kind, "{code}");
+            let back: MaintenanceError = MaintenanceError::from_io_ref(&io_err);
+            assert_eq!(back.code(), code);
+            assert_eq!(back.to_string(), message);
+        }
+    }
+
+    #[test]
+    fn io_round_trip_keeps_sqlstate_and_batch() {
+        let batch = MaintenanceError::Batch(vec![
+            MaintenanceError::NotFound("a".into()),
+            MaintenanceError::Forbidden("b".into()),
+        ]);
+        let back = MaintenanceError::from_io_ref(&batch.into());
+        assert_eq!(back.code(), "INVALID_BATCH");
+
+        let database = MaintenanceError::Database(db("57014", "statement timeout"));
+        let back = MaintenanceError::from_io_ref(&database.into());
+        assert_eq!(back.code(), "STATEMENT_TIMEOUT");
+        assert_eq!(back.sqlstate(), Some("57014"));
+    }
+
+    #[test]
+    fn plain_io_errors() {
+        let denied = io::Error::new(io::ErrorKind::PermissionDenied, "denied");
+        assert_eq!(MaintenanceError::from_io_ref(&denied).code(), "FORBIDDEN");
+        let other = io::Error::other("broken");
+        assert_eq!(MaintenanceError::from_io_ref(&other).code(), "INTERNAL");
+        let sqlx_err = io::Error::other(sqlx::Error::PoolTimedOut);
+        assert_eq!(
+            MaintenanceError::from_io_ref(&sqlx_err).code(),
+            "CONNECTION_FAILED"
+        );
+    }
+}
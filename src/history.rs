@@ -0,0 +1,207 @@
+use std::io;
+
+use chrono::DateTime;
+use chrono::Utc;
+
+use sqlx::PgPool;
+
+use async_graphql::SimpleObject;
+
+use crate::batch::MaintenanceOperation;
+use crate::batch::MaintenanceResult;
+use crate::error::MaintenanceError;
+use crate::quote_ident;
+
+pub const HISTORY_LIMIT_DEFAULT: i64 = 100;
+
+/// The statements to create the history table("{table}" and "{index}" will be replaced).
+///
+/// The statements are applied in order and must be idempotent.
+pub const MIGRATIONS: &[&str] = &[
+    r#"
+        CREATE TABLE IF NOT EXISTS {table} (
+            id BIGSERIAL PRIMARY KEY,
+            operation TEXT NOT NULL,
+            schema_name TEXT NOT NULL,
+            table_name TEXT NOT NULL,
+            sql TEXT,
+            status TEXT NOT NULL,
+            error TEXT,
+            started_at TIMESTAMPTZ,
+            duration_ms BIGINT,
+            job_id BIGINT,
+            executed_by TEXT NOT NULL DEFAULT session_user,
+            recorded_at TIMESTAMPTZ NOT NULL DEFAULT clock_timestamp()
+        )
+    "#,
+    r#"
+        CREATE INDEX IF NOT EXISTS {index}
+        ON {table} (table_name, recorded_at)
+    "#,
+];
+
+/// The caller recorded as executed_by(e.g, put into the request data by the server).
+#[derive(Clone)]
+pub struct Actor(pub String);
+
+/// The table to record the maintenance history.
+#[derive(Clone)]
+pub struct HistoryConfig {
+    pub schema: String,
+    pub table: String,
+
+    /// Recorded unless the request has an Actor(the database user if neither).
+    pub actor: Option<String>,
+}
+
+impl Default for HistoryConfig {
+    fn default() -> Self {
+        Self {
+            schema: "public".into(),
+            table: "maintenance_history".into(),
+            actor: None,
+        }
+    }
+}
+
+impl HistoryConfig {
+    /// Parses the table name(e.g, "maint.history", "history"(public schema)).
+    pub fn parse(qualified: &str) -> Result<Self, io::Error> {
+        let (schema, table) = qualified.split_once('.').unwrap_or(("public", qualified));
+        if schema.is_empty() || table.is_empty() {
+            return Err(
+                MaintenanceError::InvalidInput(format!("invalid table name: {qualified}")).into(),
+            );
+        }
+        Ok(Self {
+            schema: schema.into(),
+            table: table.into(),
+            actor: None,
+        })
+    }
+
+    pub fn qualified(&self) -> String {
+        format!("{}.{}", quote_ident(&self.schema), quote_ident(&self.table))
+    }
+}
+
+/// A recorded maintenance statement.
+#[derive(sqlx::FromRow, SimpleObject)]
+pub struct HistoryEntry {
+    pub id: i64,
+    pub operation: String,
+    pub schema_name: String,
+    pub table_name: String,
+    pub sql: Option<String>,
+    pub status: String,
+    pub error: Option<String>,
+    pub started_at: Option<DateTime<Utc>>,
+    pub duration_ms: Option<i64>,
+    pub job_id: Option<i64>,
+
+    /// The caller(or the database user if unknown) which executed the statement.
+    pub executed_by: String,
+    pub recorded_at: DateTime<Utc>,
+}
+
+pub struct PgHistory {
+    pub pool: PgPool,
+    pub config: HistoryConfig,
+}
+
+impl PgHistory {
+    /// Creates the history table if missing.
+    pub async fn migrate(&self) -> Result<(), io::Error> {
+        let table: String = self.config.qualified();
+        let index: String = quote_ident(&format!("{}_table_recorded_at", self.config.table));
+        for migration in MIGRATIONS {
+            let sql: String = migration
+                .replace("{table}", &table)
+                .replace("{index}", &index);
+            crate::execute_sql(&self.pool, &sql).await?;
+        }
+        Ok(())
+    }
+
+    /// Records the executed statement(planned or skipped results are ignored).
+    pub async fn record(
+        &self,
+        operation: MaintenanceOperation,
+        result: &MaintenanceResult,
+        job_id: Option<i64>,
+        actor: Option<&str>,
+    ) -> Result<(), io::Error> {
+        if !result.status.is_executed() {
+            return Ok(());
+        }
+        let actor: Option<&str> = actor.or(self.config.actor.as_deref());
+        let sql: String = format!(
+            r#"
+                INSERT INTO {} (
+                    operation, schema_name, table_name, sql, status,
+                    error, started_at, duration_ms, job_id, executed_by
+                )
+                VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, COALESCE($10, session_user))
+            "#,
+            self.config.qualified(),
+        );
+        sqlx::query(&sql)
+            .bind(operation.as_str())
+            .bind(&result.schema)
+            .bind(&result.table)
+            .bind(&result.sql)
+            .bind(result.status.as_str())
+            .bind(&result.error)
+            .bind(result.started_at)
+            .bind(result.duration_ms)
+            .bind(job_id)
+            .bind(actor)
+            .execute(&self.pool)
+            .await
+            .map_err(io::Error::other)?;
+        Ok(())
+    }
+
+    /// Records the results; the failures are added to the warnings of the results.
+    pub async fn record_all(
+        &self,
+        operation: MaintenanceOperation,
+        results: &mut [MaintenanceResult],
+        job_id: Option<i64>,
+        actor: Option<&str>,
+    ) {
+        for result in results {
+            if let Err(e) = self.record(operation, result, job_id, actor).await {
+                result.warnings.push(format!("history not recorded: {e}"));
+            }
+        }
+    }
+
+    /// Gets the latest entries(all tables if table is not specified).
+    pub async fn entries(
+        &self,
+        table: Option<&str>,
+        since: Option<DateTime<Utc>>,
+        limit: i64,
+    ) -> Result<Vec<HistoryEntry>, io::Error> {
+        let sql: String = format!(
+            r#"
+                SELECT *
+                FROM {}
+                WHERE
+                    ($1::TEXT IS NULL OR table_name = $1::TEXT)
+                    AND ($2::TIMESTAMPTZ IS NULL OR $2::TIMESTAMPTZ <= recorded_at)
+                ORDER BY recorded_at DESC, id DESC
+                LIMIT $3
+            "#,
+            self.config.qualified(),
+        );
+        sqlx::query_as(&sql)
+            .bind(table)
+            .bind(since)
+            .bind(limit)
+            .fetch_all(&self.pool)
+            .await
+            .map_err(io::Error::other)
+    }
+}
@@ -0,0 +1,669 @@
+use std::collections::BTreeMap;
+use std::collections::HashMap;
+use std::convert::Infallible;
+use std::io;
+use std::pin::pin;
+use std::sync::Arc;
+use std::sync::Mutex;
+use std::sync::MutexGuard;
+use std::sync::PoisonError;
+use std::sync::Weak;
+use std::sync::atomic::AtomicI64;
+use std::sync::atomic::Ordering;
+use std::time::Duration;
+use std::time::Instant;
+
+use chrono::DateTime;
+use chrono::TimeDelta;
+use chrono::Utc;
+
+use sqlx::PgPool;
+
+use tokio::sync::mpsc;
+
+use async_graphql::Enum;
+use async_graphql::SimpleObject;
+use async_graphql::futures_util::future;
+use async_graphql::futures_util::future::Either;
+
+use crate::AnalyzeOptions;
+use crate::CheckedTableName;
+use crate::PgAnalyze;
+use crate::RunSettings;
+use crate::WINDOW_CLOSED;
+use crate::batch::MaintenanceOperation;
+use crate::batch::MaintenanceResult;
+use crate::error::MaintenanceError;
+use crate::history::PgHistory;
+use crate::locks;
+use crate::locks::LockMode;
+use crate::locks::PreflightOutcome;
+use crate::retry::RetryPolicy;
+use crate::timeout;
+use crate::timeout::TimeoutPolicy;
+use crate::timeout::Timeouts;
+use crate::vacuum::PgVacuum;
+use crate::vacuum::VacuumOptions;
+use crate::window::WINDOW_POLL_INTERVAL;
+use crate::window::WindowPolicy;
+
+pub const JOB_WORKERS_DEFAULT: usize = 2;
+
+/// The interval to signal the backend again while the statement of a cancelled job runs.
+pub const CANCEL_RESIGNAL_INTERVAL: Duration = Duration::from_millis(200);
+
+pub const JOB_RETENTION_MAX_FINISHED_DEFAULT: usize = 1000;
+pub const JOB_RETENTION_MAX_AGE_DEFAULT: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// Limits the finished jobs kept in memory(the queued and running jobs are always kept).
+#[derive(Debug, Clone, Copy)]
+pub struct JobRetention {
+    /// The number of the finished jobs kept(the earliest finished evicted first).
+    pub max_finished: usize,
+
+    /// The finished jobs are evicted after this(kept until evicted by the count if None).
+    pub max_age: Option<Duration>,
+}
+
+impl Default for JobRetention {
+    fn default() -> Self {
+        Self {
+            max_finished: JOB_RETENTION_MAX_FINISHED_DEFAULT,
+            max_age: Some(JOB_RETENTION_MAX_AGE_DEFAULT),
+        }
+    }
+}
+
+impl JobRetention {
+    /// Evicts the finished jobs beyond the limits.
+    fn prune(&self, jobs: &mut BTreeMap<i64, Job>, now: DateTime<Utc>) {
+        if let Some(age) = self.max_age {
+            let age: TimeDelta = TimeDelta::from_std(age).unwrap_or(TimeDelta::MAX);
+            jobs.retain(|_, j| {
+                j.finished_at
+                    .is_none_or(|at| now.signed_duration_since(at) < age)
+            });
+        }
+        let mut finished: Vec<(DateTime<Utc>, i64)> = jobs
+            .values()
+            .filter_map(|j| j.finished_at.map(|at| (at, j.id)))
+            .collect();
+        let excess: usize = finished.len().saturating_sub(self.max_finished);
+        finished.sort_unstable();
+        for (_, id) in finished.into_iter().take(excess) {
+            jobs.remove(&id);
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Enum)]
+pub enum JobOperation {
+    Analyze,
+    Vacuum,
+}
+
+impl From<JobOperation> for MaintenanceOperation {
+    fn from(op: JobOperation) -> Self {
+        match op {
+            JobOperation::Analyze => Self::Analyze,
+            JobOperation::Vacuum => Self::Vacuum,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Enum)]
+pub enum JobStatus {
+    Queued,
+    Running,
+    Succeeded,
+    Failed,
+    Cancelled,
+}
+
+#[derive(Clone, SimpleObject)]
+pub struct JobCancellation {
+    pub requested_at: DateTime<Utc>,
+
+    /// true if the backend was terminated(pg_terminate_backend) instead of cancelled.
+    pub terminate: bool,
+
+    /// The backend which was running the statement of the job.
+    pub backend_pid: Option<i32>,
+
+    /// true if the backend was signalled successfully.
+    pub signalled: bool,
+}
+
+/// A maintenance batch executed by the in-process workers.
+#[derive(Clone, SimpleObject)]
+pub struct Job {
+    pub id: i64,
+    pub operation: JobOperation,
+    pub schema: String,
+    pub tables: Vec<String>,
+    pub status: JobStatus,
+
+    /// The ratio of the processed tables(0.0 - 1.0).
+    pub progress: f64,
+
+    /// The results of the processed tables.
+    pub results: Vec<MaintenanceResult>,
+
+    pub created_at: DateTime<Utc>,
+    pub started_at: Option<DateTime<Utc>>,
+    pub finished_at: Option<DateTime<Utc>>,
+
+    /// The backend executing the current statement.
+    pub backend_pid: Option<i32>,
+
+    pub cancellation: Option<JobCancellation>,
+
+    /// The problems which did not fail the job(e.g, history not recorded).
+    pub warnings: Vec<String>,
+}
+
+impl Job {
+    pub fn is_finished(&self) -> bool {
+        matches!(
+            self.status,
+            JobStatus::Succeeded | JobStatus::Failed | JobStatus::Cancelled
+        )
+    }
+}
+
+#[derive(Clone)]
+pub enum JobTask {
+    Analyze(AnalyzeOptions),
+    Vacuum(VacuumOptions),
+}
+
+impl JobTask {
+    pub fn operation(&self) -> JobOperation {
+        match self {
+            Self::Analyze(_) => JobOperation::Analyze,
+            Self::Vacuum(_) => JobOperation::Vacuum,
+        }
+    }
+
+    /// The lock taken on each table.
+    pub fn lock_mode(&self) -> LockMode {
+        let exclusive: bool = match self {
+            Self::Analyze(_) => false,
+            Self::Vacuum(opts) => opts.full.unwrap_or_default(),
+        };
+        LockMode::taken_by(self.operation().into(), exclusive)
+    }
+}
+
+struct QueuedJob {
+    id: i64,
+    schema: String,
+    tables: Vec<CheckedTableName>,
+    task: JobTask,
+    keep_going: bool,
+    settings: RunSettings,
+}
+
+/// Executes the statements of the jobs.
+pub struct JobRunner {
+    pub az: PgAnalyze,
+    pub vc: PgVacuum,
+    pub history: Option<Arc<PgHistory>>,
+    pub windows: Arc<WindowPolicy>,
+    pub timeouts: Arc<TimeoutPolicy>,
+    pub retry: Arc<RetryPolicy>,
+}
+
+impl JobRunner {
+    pub fn new_default(p: &PgPool) -> Self {
+        Self {
+            az: PgAnalyze::new(p),
+            vc: PgVacuum::new(p),
+            history: None,
+            windows: Arc::new(WindowPolicy::default()),
+            timeouts: Arc::new(TimeoutPolicy::default()),
+            retry: Arc::new(RetryPolicy::default()),
+        }
+    }
+
+    async fn sql(&self, table: &CheckedTableName, task: &JobTask) -> Result<String, io::Error> {
+        match task {
+            JobTask::Analyze(opts) => Ok(self.az.analyze_sql(table, &[], opts).await?),
+            JobTask::Vacuum(opts) => self.vc.vacuum_sql(table, opts).await,
+        }
+    }
+}
+
+pub fn job_not_found(id: i64) -> io::Error {
+    MaintenanceError::NotFound(format!("the job {id} not found")).into()
+}
+
+/// Keeps the jobs(in memory) and queues them to the workers.
+pub struct JobManager {
+    jobs: Mutex<BTreeMap<i64, Job>>,
+    next_id: AtomicI64,
+    sender: mpsc::UnboundedSender<QueuedJob>,
+    runner: Arc<JobRunner>,
+    retention: JobRetention,
+
+    /// The jobs waiting for their windows(not holding the workers).
+    parked: Mutex<Vec<QueuedJob>>,
+
+    /// Held while signalling a backend: the connection is not released meanwhile.
+    signalling: tokio::sync::Mutex<()>,
+}
+
+impl JobManager {
+    /// Creates the manager and spawns the workers(requires a tokio runtime).
+    pub fn start(runner: JobRunner, workers: usize, retention: JobRetention) -> Arc<Self> {
+        let (sender, receiver) = mpsc::unbounded_channel();
+        let manager = Arc::new(Self {
+            jobs: Mutex::new(BTreeMap::new()),
+            next_id: AtomicI64::new(1),
+            sender,
+            runner: Arc::new(runner),
+            retention,
+            parked: Mutex::new(vec![]),
+            signalling: tokio::sync::Mutex::new(()),
+        });
+        let receiver = Arc::new(tokio::sync::Mutex::new(receiver));
+        for _ in 0..workers.max(1) {
+            tokio::spawn(worker(Arc::downgrade(&manager), receiver.clone()));
+        }
+        tokio::spawn(releaser(Arc::downgrade(&manager)));
+        manager
+    }
+
+    fn lock(&self) -> MutexGuard<'_, BTreeMap<i64, Job>> {
+        self.jobs.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// Locks the jobs after evicting the finished jobs beyond the retention.
+    fn pruned(&self) -> MutexGuard<'_, BTreeMap<i64, Job>> {
+        let mut jobs = self.lock();
+        self.retention.prune(&mut jobs, Utc::now());
+        jobs
+    }
+
+    fn parked(&self) -> MutexGuard<'_, Vec<QueuedJob>> {
+        self.parked.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// true if the window of the job is open(or the job was cancelled while waiting).
+    fn is_runnable(&self, q: &QueuedJob, now: DateTime<Utc>) -> bool {
+        let operation: MaintenanceOperation = q.task.operation().into();
+        self.runner.windows.is_open(operation, now) || self.cancel_requested(q.id)
+    }
+
+    /// Sends the runnable job to the workers(or keeps it until its window opens).
+    fn dispatch(&self, q: QueuedJob) -> Result<(), io::Error> {
+        match self.is_runnable(&q, Utc::now()) {
+            true => self
+                .sender
+                .send(q)
+                .map_err(|_| io::Error::other("the job workers stopped")),
+            false => {
+                self.parked().push(q);
+                Ok(())
+            }
+        }
+    }
+
+    /// Sends the parked jobs which became runnable to the workers.
+    fn release_parked(&self) {
+        let now: DateTime<Utc> = Utc::now();
+        let runnable: Vec<QueuedJob> = {
+            let mut parked = self.parked();
+            let (runnable, waiting) = std::mem::take(&mut *parked)
+                .into_iter()
+                .partition(|q| self.is_runnable(q, now));
+            *parked = waiting;
+            runnable
+        };
+        for q in runnable {
+            if self.sender.send(q).is_err() {
+                // the workers stopped: the manager is being dropped
+                return;
+            }
+        }
+    }
+
+    fn update<F, T>(&self, id: i64, f: F) -> Option<T>
+    where
+        F: FnOnce(&mut Job) -> T,
+    {
+        self.lock().get_mut(&id).map(f)
+    }
+
+    /// Queues the checked tables and returns the job immediately.
+    ///
+    /// The job is rejected outside the maintenance window unless the policy queues it.
+    pub fn submit(
+        &self,
+        schema: String,
+        tables: Vec<CheckedTableName>,
+        task: JobTask,
+        keep_going: bool,
+        settings: RunSettings,
+    ) -> Result<Job, io::Error> {
+        let operation: MaintenanceOperation = task.operation().into();
+        self.runner.windows.check_submit(operation)?;
+        let id: i64 = self.next_id.fetch_add(1, Ordering::SeqCst);
+        let job = Job {
+            id,
+            operation: task.operation(),
+            schema: schema.clone(),
+            tables: tables.iter().map(|t| t.as_str().into()).collect(),
+            status: JobStatus::Queued,
+            progress: 0.0,
+            results: vec![],
+            created_at: Utc::now(),
+            started_at: None,
+            finished_at: None,
+            backend_pid: None,
+            cancellation: None,
+            warnings: vec![],
+        };
+        self.pruned().insert(id, job.clone());
+        let queued = QueuedJob {
+            id,
+            schema,
+            tables,
+            task,
+            keep_going,
+            settings,
+        };
+        self.dispatch(queued).inspect_err(|_| {
+            self.lock().remove(&id);
+        })?;
+        Ok(job)
+    }
+
+    pub fn job(&self, id: i64) -> Option<Job> {
+        self.pruned().get(&id).cloned()
+    }
+
+    /// Gets the jobs(all if the status is not specified) in the submitted order.
+    pub fn jobs(&self, status: Option<JobStatus>) -> Vec<Job> {
+        self.pruned()
+            .values()
+            .filter(|j| status.is_none_or(|s| s == j.status))
+            .cloned()
+            .collect()
+    }
+
+    /// Requests the job to stop and signals the backend running its statement.
+    pub async fn cancel(&self, id: i64, terminate: bool) -> Result<Job, io::Error> {
+        // the backend keeps running the statement of the job(or idle) until released
+        let _signalling = self.signalling.lock().await;
+        let pid: Option<i32> = {
+            let mut jobs = self.lock();
+            let job: &mut Job = jobs.get_mut(&id).ok_or_else(|| job_not_found(id))?;
+            if job.is_finished() {
+                return Err(MaintenanceError::InvalidInput(format!(
+                    "the job {id} already finished"
+                ))
+                .into());
+            }
+            job.cancellation = Some(JobCancellation {
+                requested_at: Utc::now(),
+                terminate,
+                backend_pid: job.backend_pid,
+                signalled: false,
+            });
+            job.backend_pid
+        };
+
+        if let Some(pid) = pid {
+            let signalled: bool =
+                crate::signal_backend(&self.runner.az.pool, pid, terminate).await?;
+            self.update(id, |j| {
+                if let Some(c) = j.cancellation.as_mut() {
+                    c.signalled = signalled;
+                }
+            });
+        }
+
+        self.job(id).ok_or_else(|| job_not_found(id))
+    }
+
+    /// Maps the backend pids to the jobs running statements on them.
+    pub fn running_pids(&self) -> HashMap<i32, i64> {
+        self.lock()
+            .values()
+            .filter_map(|j| j.backend_pid.map(|pid| (pid, j.id)))
+            .collect()
+    }
+
+    fn cancel_requested(&self, id: i64) -> bool {
+        self.update(id, |j| j.cancellation.is_some())
+            .unwrap_or_default()
+    }
+
+    /// Executes the statement(retried on the transient failures) with the number of the attempts.
+    async fn execute(
+        &self,
+        id: i64,
+        table: &CheckedTableName,
+        task: &JobTask,
+        requested: Option<Timeouts>,
+    ) -> (Result<String, io::Error>, i32) {
+        let sql: String = match self.runner.sql(table, task).await {
+            Ok(sql) => sql,
+            Err(e) => return (Err(e), 0),
+        };
+        let operation: MaintenanceOperation = task.operation().into();
+        let timeouts: Timeouts = self.runner.timeouts.timeouts(operation, requested);
+        let (res, attempts) = self
+            .runner
+            .retry
+            .run(|| self.execute_once(id, operation, &sql, &timeouts))
+            .await;
+        (res.map(|_| sql), attempts)
+    }
+
+    /// Executes the statement on a dedicated connection whose backend pid is recorded.
+    async fn execute_once(
+        &self,
+        id: i64,
+        operation: MaintenanceOperation,
+        sql: &str,
+        timeouts: &Timeouts,
+    ) -> Result<(), io::Error> {
+        let mut conn = self
+            .runner
+            .az
+            .pool
+            .acquire()
+            .await
+            .map_err(io::Error::other)?;
+        let pid: i32 = crate::backend_pid(&mut conn).await?;
+        let cancelled: bool = self
+            .update(id, |j| {
+                j.backend_pid = Some(pid);
+                j.cancellation.is_some()
+            })
+            .unwrap_or_default();
+        let res: Result<(), io::Error> = match cancelled {
+            true => Err(io::Error::other(format!("the job {id} was cancelled"))),
+            false => {
+                let executed = timeout::execute_with_timeouts(&mut conn, operation, sql, timeouts);
+                match future::select(pin!(executed), pin!(self.resignal(id, pid))).await {
+                    Either::Left((res, _)) => res.map_err(io::Error::from),
+                    Either::Right((never, _)) => match never {},
+                }
+            }
+        };
+        // waits for the signal in flight(if any) before releasing the connection
+        let _signalling = self.signalling.lock().await;
+        let cancel_requested: bool = self
+            .update(id, |j| {
+                j.backend_pid = None;
+                j.cancellation.is_some()
+            })
+            .unwrap_or_default();
+        if (res.is_err() && !timeouts.is_empty()) || cancel_requested {
+            // the settings of the session may not be reset(or the backend may be signalled)
+            conn.close_on_drop();
+        }
+        res
+    }
+
+    /// Signals the backend while the statement runs if the job is cancelled.
+    ///
+    /// The cancel sent by cancelJob is ignored if it reaches the backend before the statement.
+    async fn resignal(&self, id: i64, pid: i32) -> Infallible {
+        loop {
+            tokio::time::sleep(CANCEL_RESIGNAL_INTERVAL).await;
+            let terminate: Option<bool> = self
+                .update(id, |j| j.cancellation.as_ref().map(|c| c.terminate))
+                .flatten();
+            let Some(terminate) = terminate else {
+                continue;
+            };
+            let pool: &PgPool = &self.runner.az.pool;
+            if crate::signal_backend(pool, pid, terminate)
+                .await
+                .unwrap_or_default()
+            {
+                self.update(id, |j| {
+                    if let Some(c) = j.cancellation.as_mut() {
+                        c.signalled = true;
+                    }
+                });
+            }
+        }
+    }
+
+    /// Checks the conflicting locks(the skipped result if the table is skipped).
+    async fn preflight(
+        &self,
+        q: &QueuedJob,
+        table: &CheckedTableName,
+    ) -> Result<Option<MaintenanceResult>, io::Error> {
+        let Some(pf) = q.settings.preflight.as_ref() else {
+            return Ok(None);
+        };
+        let lock: LockMode = q.task.lock_mode();
+        let pool: &PgPool = &self.runner.az.pool;
+        match locks::preflight(pool, table.schema(), table.as_str(), lock, pf).await? {
+            PreflightOutcome::Clear => Ok(None),
+            PreflightOutcome::Skip(blockers) => Ok(Some(MaintenanceResult::locked(
+                q.schema.clone(),
+                table.as_str().into(),
+                blockers,
+            ))),
+        }
+    }
+
+    async fn record(&self, q: &QueuedJob, result: &MaintenanceResult) {
+        let Some(history) = self.runner.history.as_ref() else {
+            return;
+        };
+        let operation: MaintenanceOperation = q.task.operation().into();
+        let actor: Option<&str> = q.settings.actor.as_deref();
+        if let Err(e) = history.record(operation, result, Some(q.id), actor).await {
+            let warning: String = format!("history of {} not recorded: {e}", result.table);
+            self.update(q.id, |j| j.warnings.push(warning));
+        }
+    }
+
+    async fn run(&self, q: QueuedJob) {
+        let operation: MaintenanceOperation = q.task.operation().into();
+        self.update(q.id, |j| {
+            j.status = JobStatus::Running;
+            j.started_at = Some(Utc::now());
+        });
+
+        let total: usize = q.tables.len();
+        let mut failed: bool = false;
+        let mut window_closed: bool = false;
+        for (i, table) in q.tables.iter().enumerate() {
+            let name: String = table.as_str().into();
+            let skip: bool = (failed && !q.keep_going) || self.cancel_requested(q.id);
+            window_closed |= !skip && !self.runner.windows.is_open(operation, Utc::now());
+            let result: MaintenanceResult = match (skip, window_closed) {
+                (true, _) => MaintenanceResult::skipped(q.schema.clone(), name),
+                (false, true) => {
+                    let reason: String = WINDOW_CLOSED.into();
+                    MaintenanceResult::skipped_because(q.schema.clone(), name, reason)
+                }
+                (false, false) => match self.preflight(&q, table).await {
+                    Ok(Some(skipped)) => skipped,
+                    checked => {
+                        let started_at = Utc::now();
+                        let started = Instant::now();
+                        let (res, attempts) = match checked {
+                            Ok(_) => {
+                                self.execute(q.id, table, &q.task, q.settings.timeouts)
+                                    .await
+                            }
+                            Err(e) => (Err(e), 0),
+                        };
+                        failed |= res.is_err();
+                        let elapsed = started.elapsed();
+                        let result = MaintenanceResult::new(
+                            q.schema.clone(),
+                            name,
+                            started_at,
+                            elapsed,
+                            res,
+                        )
+                        .with_attempts(attempts);
+                        self.record(&q, &result).await;
+                        result
+                    }
+                },
+            };
+            self.update(q.id, |j| {
+                j.results.push(result);
+                j.progress = ((i + 1) as f64) / (total as f64);
+            });
+        }
+
+        self.update(q.id, |j| {
+            if window_closed {
+                j.warnings.push(WINDOW_CLOSED.into());
+            }
+            j.status = match (j.cancellation.is_some(), failed || window_closed) {
+                (true, _) => JobStatus::Cancelled,
+                (false, true) => JobStatus::Failed,
+                (false, false) => JobStatus::Succeeded,
+            };
+            j.progress = 1.0;
+            j.finished_at = Some(Utc::now());
+        });
+    }
+}
+
+async fn worker(
+    manager: Weak<JobManager>,
+    receiver: Arc<tokio::sync::Mutex<mpsc::UnboundedReceiver<QueuedJob>>>,
+) {
+    loop {
+        let next: Option<QueuedJob> = receiver.lock().await.recv().await;
+        let Some(q) = next else {
+            return;
+        };
+        let Some(m) = manager.upgrade() else {
+            return;
+        };
+        match m.is_runnable(&q, Utc::now()) {
+            true => m.run(q).await,
+            // the window closed while the job was in the channel
+            false => m.parked().push(q),
+        }
+    }
+}
+
+/// Releases the parked jobs once their windows open.
+async fn releaser(manager: Weak<JobManager>) {
+    let mut interval = tokio::time::interval(WINDOW_POLL_INTERVAL);
+    loop {
+        interval.tick().await;
+        let Some(m) = manager.upgrade() else {
+            return;
+        };
+        m.release_parked();
+    }
+}
@@ -1,22 +1,197 @@
+use std::cmp::Reverse;
+use std::collections::HashMap;
 use std::io;
+use std::sync::Arc;
+use std::sync::atomic::AtomicBool;
+use std::sync::atomic::Ordering;
+use std::time::Duration;
+use std::time::Instant;
 
+use chrono::DateTime;
+use chrono::Utc;
+
+use tokio::sync::Semaphore;
+
+use sqlx::PgConnection;
 use sqlx::PgPool;
 
 use async_graphql::futures_util;
 
+use futures_util::Stream;
 use futures_util::StreamExt;
 use futures_util::TryStreamExt;
+use futures_util::future::join_all;
 
-use async_graphql::EmptySubscription;
+use async_graphql::Context;
+use async_graphql::ErrorExtensions;
+use async_graphql::InputObject;
 use async_graphql::Object;
 use async_graphql::Schema;
+use async_graphql::Subscription;
+use async_graphql::dataloader::DataLoader;
+
+pub mod batch;
+pub mod config;
+pub mod error;
+pub mod history;
+pub mod job;
+pub mod loader;
+pub mod locks;
+pub mod matview;
+pub mod policy;
+pub mod progress;
+pub mod reindex;
+pub mod relation;
+pub mod retry;
+pub mod schedule;
+pub mod stale;
+pub mod stats;
+pub mod timeout;
+pub mod vacuum;
+pub mod window;
+
+use batch::MaintenanceOperation;
+use batch::MaintenanceResult;
+use batch::SCHEMA_WIDE;
+
+use config::MaintenanceConfig;
+
+use error::ErrorCodes;
+use error::MaintenanceError;
+
+use history::Actor;
+use history::HISTORY_LIMIT_DEFAULT;
+use history::HistoryEntry;
+use history::PgHistory;
+
+use job::JOB_WORKERS_DEFAULT;
+use job::Job;
+use job::JobManager;
+use job::JobRetention;
+use job::JobRunner;
+use job::JobStatus;
+use job::JobTask;
+
+use loader::TableExistsLoader;
+use loader::TableKey;
+
+use locks::Blocker;
+use locks::LockMode;
+use locks::Preflight;
+use locks::PreflightOutcome;
+
+use matview::CheckedMatViewName;
+use matview::MatViewNameChecker;
+use matview::MatViewRefresh;
+use matview::PgRefresh;
+use matview::UncheckedMatViewName;
+
+use policy::PolicyChecker;
+use policy::TablePolicy;
+
+use progress::MaintenanceProgress;
+
+use reindex::CheckedIndexName;
+use reindex::IndexNameChecker;
+use reindex::PgIdxChk;
+use reindex::PgReindex;
+use reindex::UncheckedIndexName;
+
+use relation::PgRelChk;
+use relation::RelKind;
+
+use retry::RetryPolicy;
+
+use schedule::Schedule;
+use schedule::ScheduleInput;
+use schedule::Scheduler;
+
+use stale::StaleTable;
+
+use stats::TableStats;
+
+use timeout::TimeoutPolicy;
+use timeout::Timeouts;
+
+use vacuum::PgVacuum;
+use vacuum::VacuumOptions;
+
+use window::WindowPolicy;
+use window::WindowState;
+
+/// Quotes an identifier the same way as `quote_ident` (always quoted).
+pub fn quote_ident(ident: &str) -> String {
+    format!("\"{}\"", ident.replace('"', "\"\""))
+}
 
 pub struct UncheckedTableName(pub String);
-pub struct CheckedTableName(String);
+
+/// A table name which was validated within its schema.
+pub struct CheckedTableName {
+    schema: String,
+    name: String,
+    kind: RelKind,
+}
 
 impl CheckedTableName {
     pub fn as_str(&self) -> &str {
-        &self.0
+        &self.name
+    }
+
+    pub fn schema(&self) -> &str {
+        &self.schema
+    }
+
+    pub fn kind(&self) -> RelKind {
+        self.kind
+    }
+
+    /// The quoted, schema-qualified name(e.g, "public"."MyTable").
+    pub fn qualified(&self) -> String {
+        format!("{}.{}", quote_ident(&self.schema), quote_ident(&self.name))
+    }
+
+    /// Accepts the relation if it can be maintained as a table.
+    pub fn from_kind(
+        schema: &str,
+        unchecked: UncheckedTableName,
+        kind: Option<RelKind>,
+    ) -> Result<Self, MaintenanceError> {
+        let raw_name: &str = &unchecked.0;
+        match kind {
+            Some(RelKind::View) => Err(MaintenanceError::InvalidInput(format!(
+                "the relation {raw_name} is a view, not a table"
+            ))),
+            Some(k) if k.is_analyzable() => Ok(Self {
+                schema: schema.into(),
+                name: unchecked.0,
+                kind: k,
+            }),
+            Some(k) => Err(MaintenanceError::InvalidInput(format!(
+                "the relation {raw_name} is not a table: {k:?}"
+            ))),
+            None => Err(MaintenanceError::NotFound(format!(
+                "the table {raw_name} not found"
+            ))),
+        }
+    }
+}
+
+/// Joins the errors of the invalid names(or returns all checked names).
+fn all_or_nothing<T>(
+    results: Vec<Result<T, MaintenanceError>>,
+) -> Result<Vec<T>, MaintenanceError> {
+    let mut checked: Vec<T> = Vec::with_capacity(results.len());
+    let mut errors: Vec<MaintenanceError> = vec![];
+    for r in results {
+        match r {
+            Ok(t) => checked.push(t),
+            Err(e) => errors.push(e),
+        }
+    }
+    match errors.is_empty() {
+        true => Ok(checked),
+        false => Err(MaintenanceError::Batch(errors)),
     }
 }
 
@@ -26,12 +201,64 @@ pub trait TableNameChecker: Sync + Send + 'static {
         &self,
         schema: &str,
         unchecked: UncheckedTableName,
-    ) -> Result<CheckedTableName, io::Error>;
+    ) -> Result<CheckedTableName, MaintenanceError>;
+
+    /// Checks all names before returning; rejects the whole batch if any name is invalid.
+    async fn check_table_names(
+        &self,
+        schema: &str,
+        unchecked: Vec<UncheckedTableName>,
+    ) -> Result<Vec<CheckedTableName>, MaintenanceError> {
+        let mut results: Vec<Result<CheckedTableName, MaintenanceError>> =
+            Vec::with_capacity(unchecked.len());
+        for u in unchecked {
+            results.push(self.check_table_name(schema, u).await);
+        }
+        all_or_nothing(results)
+    }
 }
 
 #[async_trait::async_trait]
 pub trait TableChecker: Sync + Send + 'static {
-    async fn table_exists(&self, schema: &str, name: &str) -> Result<bool, io::Error>;
+    /// Gets the kind of the relation(None if not found).
+    async fn table_kind(
+        &self,
+        schema: &str,
+        name: &str,
+    ) -> Result<Option<RelKind>, MaintenanceError>;
+
+    /// true if the relation exists as a table(see RelKind::is_table_like).
+    async fn table_exists(&self, schema: &str, name: &str) -> Result<bool, MaintenanceError> {
+        let kind: Option<RelKind> = self.table_kind(schema, name).await?;
+        Ok(kind.is_some_and(|k| k.is_table_like()))
+    }
+
+    async fn tables_exist(
+        &self,
+        schema: &str,
+        names: &[String],
+    ) -> Result<HashMap<String, bool>, MaintenanceError> {
+        let mut found: HashMap<String, bool> = HashMap::with_capacity(names.len());
+        for name in names {
+            found.insert(name.clone(), self.table_exists(schema, name).await?);
+        }
+        Ok(found)
+    }
+
+    /// Gets the kinds of the relations(unknown names are absent).
+    async fn table_kinds(
+        &self,
+        schema: &str,
+        names: &[String],
+    ) -> Result<HashMap<String, RelKind>, MaintenanceError> {
+        let mut kinds: HashMap<String, RelKind> = HashMap::with_capacity(names.len());
+        for name in names {
+            if let Some(k) = self.table_kind(schema, name).await? {
+                kinds.insert(name.clone(), k);
+            }
+        }
+        Ok(kinds)
+    }
 }
 
 #[async_trait::async_trait]
@@ -43,34 +270,152 @@ where
         &self,
         schema: &str,
         unchecked: UncheckedTableName,
-    ) -> Result<CheckedTableName, io::Error> {
+    ) -> Result<CheckedTableName, MaintenanceError> {
+        let kind: Option<RelKind> = self.table_kind(schema, &unchecked.0).await?;
+        CheckedTableName::from_kind(schema, unchecked, kind)
+    }
+
+    async fn check_table_names(
+        &self,
+        schema: &str,
+        unchecked: Vec<UncheckedTableName>,
+    ) -> Result<Vec<CheckedTableName>, MaintenanceError> {
+        let names: Vec<String> = unchecked.iter().map(|u| u.0.clone()).collect();
+        let kinds: HashMap<String, RelKind> = self.table_kinds(schema, &names).await?;
+        let results: Vec<Result<CheckedTableName, MaintenanceError>> = unchecked
+            .into_iter()
+            .map(|u| {
+                let kind: Option<RelKind> = kinds.get(&u.0).copied();
+                CheckedTableName::from_kind(schema, u, kind)
+            })
+            .collect();
+        all_or_nothing(results)
+    }
+}
+
+pub struct PgTabChk {
+    pub pool: PgPool,
+}
+
+#[async_trait::async_trait]
+impl TableChecker for PgTabChk {
+    async fn table_kind(
+        &self,
+        schema: &str,
+        name: &str,
+    ) -> Result<Option<RelKind>, MaintenanceError> {
+        Ok(relation::relation_kind(&self.pool, schema, name).await?)
+    }
+
+    async fn table_kinds(
+        &self,
+        schema: &str,
+        names: &[String],
+    ) -> Result<HashMap<String, RelKind>, MaintenanceError> {
+        Ok(relation::relation_kinds(&self.pool, schema, names).await?)
+    }
+
+    async fn tables_exist(
+        &self,
+        schema: &str,
+        names: &[String],
+    ) -> Result<HashMap<String, bool>, MaintenanceError> {
+        let kinds: HashMap<String, RelKind> = self.table_kinds(schema, names).await?;
+        Ok(names
+            .iter()
+            .map(|n| {
+                let found: bool = kinds.get(n).is_some_and(|k| k.is_table_like());
+                (n.clone(), found)
+            })
+            .collect())
+    }
+}
+
+pub struct UncheckedColumnName(pub String);
+
+/// A column name which was validated within its table.
+pub struct CheckedColumnName(String);
+
+impl CheckedColumnName {
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+
+    /// The quoted name(e.g, "MyColumn").
+    pub fn quoted(&self) -> String {
+        quote_ident(&self.0)
+    }
+}
+
+#[async_trait::async_trait]
+pub trait ColumnNameChecker: Sync + Send + 'static {
+    async fn check_column_name(
+        &self,
+        table: &CheckedTableName,
+        unchecked: UncheckedColumnName,
+    ) -> Result<CheckedColumnName, io::Error>;
+}
+
+#[async_trait::async_trait]
+pub trait ColumnChecker: Sync + Send + 'static {
+    async fn column_exists(&self, schema: &str, table: &str, name: &str)
+    -> Result<bool, io::Error>;
+}
+
+#[async_trait::async_trait]
+impl<C> ColumnNameChecker for C
+where
+    C: ColumnChecker,
+{
+    async fn check_column_name(
+        &self,
+        table: &CheckedTableName,
+        unchecked: UncheckedColumnName,
+    ) -> Result<CheckedColumnName, io::Error> {
         let raw_name: &str = &unchecked.0;
-        let found: bool = self.table_exists(schema, raw_name).await?;
+        let found: bool = self
+            .column_exists(table.schema(), table.as_str(), raw_name)
+            .await?;
         if !found {
-            return Err(io::Error::other(format!("the table {raw_name} not found")));
+            return Err(MaintenanceError::NotFound(format!(
+                "the column {raw_name} not found in the table {}",
+                table.as_str(),
+            ))
+            .into());
         }
-        Ok(CheckedTableName(unchecked.0))
+        Ok(CheckedColumnName(unchecked.0))
     }
 }
 
-pub struct PgTabChk {
+pub struct PgColChk {
     pub pool: PgPool,
 }
 
 #[async_trait::async_trait]
-impl TableChecker for PgTabChk {
-    async fn table_exists(&self, schema: &str, name: &str) -> Result<bool, io::Error> {
+impl ColumnChecker for PgColChk {
+    async fn column_exists(
+        &self,
+        schema: &str,
+        table: &str,
+        name: &str,
+    ) -> Result<bool, io::Error> {
         let p: &PgPool = &self.pool;
 
         let oi: Option<i32> = sqlx::query_scalar!(
             r#"(
                 SELECT 1::INTEGER AS one
-                FROM information_schema.tables
+                FROM pg_attribute a
+                INNER JOIN pg_class c ON c.oid = a.attrelid
+                INNER JOIN pg_namespace n ON n.oid = c.relnamespace
                 WHERE
-                    table_schema = $1::TEXT
-                    AND table_name = $2::TEXT
+                    n.nspname = $1::TEXT
+                    AND c.relname = $2::TEXT
+                    AND a.attname = $3::TEXT
+                    AND 0 < a.attnum
+                    AND NOT a.attisdropped
             )"#,
             schema,
+            table,
             name,
         )
         .fetch_optional(p)
@@ -86,64 +431,1165 @@ impl TableChecker for PgTabChk {
     }
 }
 
+pub const PG_VERSION_SKIP_LOCKED: i32 = 120000;
+pub const PG_VERSION_BUFFER_USAGE_LIMIT: i32 = 160000;
+
+/// Checks the buffer size(e.g, 256kB, 16MB, 1024).
+fn valid_buffer_size(size: &str) -> bool {
+    let num_len: usize = size.chars().take_while(|c| c.is_ascii_digit()).count();
+    let unit: &str = &size[num_len..];
+    0 < num_len && ["", "kB", "MB", "GB", "TB"].contains(&unit)
+}
+
+#[derive(Clone, Default, InputObject)]
+pub struct AnalyzeOptions {
+    pub verbose: Option<bool>,
+
+    /// Requires PostgreSQL 12 or later.
+    pub skip_locked: Option<bool>,
+
+    /// The ring buffer size(e.g, 256kB, 16MB). Requires PostgreSQL 16 or later.
+    pub buffer_usage_limit: Option<String>,
+
+    /// Analyzes foreign tables via their FDW(rejected by default).
+    pub allow_foreign_table: Option<bool>,
+}
+
+impl AnalyzeOptions {
+    /// Creates the option list(e.g, "(VERBOSE, SKIP_LOCKED)") for the server.
+    pub fn to_sql(&self, server_version_num: i32) -> Result<String, MaintenanceError> {
+        let mut opts: Vec<String> = vec![];
+
+        if self.verbose.unwrap_or_default() {
+            opts.push("VERBOSE".into());
+        }
+
+        if self.skip_locked.unwrap_or_default() {
+            if server_version_num < PG_VERSION_SKIP_LOCKED {
+                return Err(MaintenanceError::Unsupported(format!(
+                    "SKIP_LOCKED not supported: server version {server_version_num}"
+                )));
+            }
+            opts.push("SKIP_LOCKED".into());
+        }
+
+        if let Some(size) = &self.buffer_usage_limit {
+            if server_version_num < PG_VERSION_BUFFER_USAGE_LIMIT {
+                return Err(MaintenanceError::Unsupported(format!(
+                    "BUFFER_USAGE_LIMIT not supported: server version {server_version_num}"
+                )));
+            }
+            if !valid_buffer_size(size) {
+                return Err(MaintenanceError::InvalidInput(format!(
+                    "invalid buffer size: {size}"
+                )));
+            }
+            opts.push(format!("BUFFER_USAGE_LIMIT '{size}'"));
+        }
+
+        if opts.is_empty() {
+            return Ok("".into());
+        }
+        Ok(format!("({})", opts.join(", ")))
+    }
+}
+
+pub async fn server_version_num(p: &PgPool) -> Result<i32, io::Error> {
+    let oi: Option<i32> = sqlx::query_scalar!(
+        r#"(
+            SELECT current_setting('server_version_num')::INTEGER AS ver
+        )"#,
+    )
+    .fetch_one(p)
+    .await
+    .map_err(io::Error::other)?;
+    oi.ok_or(io::Error::other("server version expected"))
+}
+
+/// The server version looked up on the first use(restart the service after upgrading the server).
+#[derive(Default)]
+pub struct ServerVersion(tokio::sync::OnceCell<i32>);
+
+impl ServerVersion {
+    pub async fn get(&self, p: &PgPool) -> Result<i32, io::Error> {
+        self.0
+            .get_or_try_init(|| server_version_num(p))
+            .await
+            .copied()
+    }
+}
+
+/// Joins the non-empty parts of a statement(e.g, ["ANALYZE", "", "t"] -> "ANALYZE t").
+pub fn join_sql(parts: &[&str]) -> String {
+    let nonempty: Vec<&str> = parts.iter().copied().filter(|s| !s.is_empty()).collect();
+    nonempty.join(" ")
+}
+
+/// Executes a maintenance statement built from checked names.
+pub async fn execute_sql<'e, E>(e: E, sql: &str) -> Result<(), io::Error>
+where
+    E: sqlx::PgExecutor<'e>,
+{
+    sqlx::query(sql)
+        .execute(e)
+        .await
+        .map_err(io::Error::other)?;
+    Ok(())
+}
+
+/// Gets the PID of the backend serving the connection.
+pub async fn backend_pid(conn: &mut PgConnection) -> Result<i32, io::Error> {
+    let oi: Option<i32> = sqlx::query_scalar!(
+        r#"(
+            SELECT pg_backend_pid() AS pid
+        )"#,
+    )
+    .fetch_one(conn)
+    .await
+    .map_err(io::Error::other)?;
+    oi.ok_or(io::Error::other("backend pid expected"))
+}
+
+/// Cancels the current statement of the backend(or terminates the backend).
+pub async fn signal_backend(p: &PgPool, pid: i32, terminate: bool) -> Result<bool, io::Error> {
+    let ob: Option<bool> = match terminate {
+        false => sqlx::query_scalar!(
+            r#"(
+                SELECT pg_cancel_backend($1::INTEGER) AS signalled
+            )"#,
+            pid,
+        )
+        .fetch_one(p)
+        .await
+        .map_err(io::Error::other)?,
+        true => sqlx::query_scalar!(
+            r#"(
+                SELECT pg_terminate_backend($1::INTEGER) AS signalled
+            )"#,
+            pid,
+        )
+        .fetch_one(p)
+        .await
+        .map_err(io::Error::other)?,
+    };
+    Ok(ob.unwrap_or_default())
+}
+
 pub struct PgAnalyze {
     pub pool: PgPool,
+    version: ServerVersion,
 }
 
 impl PgAnalyze {
-    pub async fn analyze(&self, table: &CheckedTableName) -> Result<(), io::Error> {
-        let p: &PgPool = &self.pool;
-        sqlx::query(&format!("ANALYZE {}", table.0))
-            .execute(p)
+    pub fn new(p: &PgPool) -> Self {
+        Self {
+            pool: p.clone(),
+            version: ServerVersion::default(),
+        }
+    }
+
+    /// Gets the server version(cached: not looked up for each table).
+    pub async fn server_version_num(&self) -> Result<i32, MaintenanceError> {
+        Ok(self.version.get(&self.pool).await?)
+    }
+
+    /// Creates the statement to analyze the columns(all columns if empty).
+    pub async fn analyze_sql(
+        &self,
+        table: &CheckedTableName,
+        columns: &[CheckedColumnName],
+        opts: &AnalyzeOptions,
+    ) -> Result<String, MaintenanceError> {
+        if RelKind::ForeignTable == table.kind() && !opts.allow_foreign_table.unwrap_or_default() {
+            return Err(MaintenanceError::InvalidInput(format!(
+                "the table {} is a foreign table: set allowForeignTable to analyze via its FDW",
+                table.as_str(),
+            )));
+        }
+        let ver: i32 = self.server_version_num().await?;
+        let opt_sql: String = opts.to_sql(ver)?;
+        let col_sql: String = match columns.is_empty() {
+            true => "".into(),
+            false => {
+                let quoted: Vec<String> = columns.iter().map(|c| c.quoted()).collect();
+                format!("({})", quoted.join(", "))
+            }
+        };
+        Ok(join_sql(&[
+            "ANALYZE",
+            &opt_sql,
+            &table.qualified(),
+            &col_sql,
+        ]))
+    }
+
+    pub async fn analyze(
+        &self,
+        table: &CheckedTableName,
+        opts: &AnalyzeOptions,
+    ) -> Result<(), MaintenanceError> {
+        self.analyze_columns(table, &[], opts).await
+    }
+
+    /// Analyzes the specified columns only(all columns if empty).
+    pub async fn analyze_columns(
+        &self,
+        table: &CheckedTableName,
+        columns: &[CheckedColumnName],
+        opts: &AnalyzeOptions,
+    ) -> Result<(), MaintenanceError> {
+        self.analyze_with_timeouts(table, columns, opts, &Timeouts::default())
             .await
-            .map_err(io::Error::other)?;
-        Ok(())
+    }
+
+    /// Analyzes the columns in a transaction with the timeouts(SET LOCAL).
+    pub async fn analyze_with_timeouts(
+        &self,
+        table: &CheckedTableName,
+        columns: &[CheckedColumnName],
+        opts: &AnalyzeOptions,
+        timeouts: &Timeouts,
+    ) -> Result<(), MaintenanceError> {
+        let sql: String = self.analyze_sql(table, columns, opts).await?;
+        self.execute(&sql, timeouts).await
+    }
+
+    /// Executes the ANALYZE statement with the timeouts.
+    pub async fn execute(&self, sql: &str, timeouts: &Timeouts) -> Result<(), MaintenanceError> {
+        timeout::execute_pooled(&self.pool, MaintenanceOperation::Analyze, sql, timeouts).await
     }
 }
 
 pub struct MutationRoot {
     pub checker: Box<dyn TableNameChecker>,
+    pub col_checker: Box<dyn ColumnNameChecker>,
+    pub idx_checker: Box<dyn IndexNameChecker>,
+    pub mv_checker: Box<dyn MatViewNameChecker>,
     pub az: PgAnalyze,
+    pub vc: PgVacuum,
+    pub ri: PgReindex,
+    pub rf: PgRefresh,
+    pub jobs: Arc<JobManager>,
+    pub scheduler: Arc<Scheduler>,
+    pub history: Option<Arc<PgHistory>>,
+    pub windows: Arc<WindowPolicy>,
+
+    /// Also applied to the relations not checked by the checker(e.g, indexes).
+    pub policy: Arc<TablePolicy>,
+
+    pub timeouts: Arc<TimeoutPolicy>,
+    pub retry: Arc<RetryPolicy>,
 }
 
 impl MutationRoot {
-    pub fn new_default(p: &PgPool) -> Self {
+    /// Creates the root with the default policy(system schemas denied).
+    pub fn new_default(p: &PgPool, jobs: Arc<JobManager>, scheduler: Arc<Scheduler>) -> Self {
+        Self::new_with_policy(p, jobs, scheduler, Arc::new(TablePolicy::default()))
+    }
+
+    pub fn new_with_policy(
+        p: &PgPool,
+        jobs: Arc<JobManager>,
+        scheduler: Arc<Scheduler>,
+        policy: Arc<TablePolicy>,
+    ) -> Self {
         let chk = PgTabChk { pool: p.clone() };
         Self {
-            checker: Box::new(chk),
-            az: PgAnalyze { pool: p.clone() },
+            checker: Box::new(PolicyChecker {
+                inner: chk,
+                policy: policy.clone(),
+            }),
+            col_checker: Box::new(PgColChk { pool: p.clone() }),
+            idx_checker: Box::new(PgIdxChk { pool: p.clone() }),
+            mv_checker: Box::new(PgRelChk { pool: p.clone() }),
+            az: PgAnalyze::new(p),
+            vc: PgVacuum::new(p),
+            ri: PgReindex::new(p),
+            rf: PgRefresh { pool: p.clone() },
+            jobs,
+            scheduler,
+            history: None,
+            windows: Arc::new(WindowPolicy::default()),
+            policy,
+            timeouts: Arc::new(TimeoutPolicy::default()),
+            retry: Arc::new(RetryPolicy::default()),
         }
     }
 }
 
-#[Object]
+pub const WINDOW_CLOSED: &str = "the maintenance window closed";
+
+/// How the statement of each relation is run.
+#[derive(Clone, Default)]
+pub struct RunSettings {
+    pub dry_run: bool,
+    pub timeouts: Option<Timeouts>,
+
+    /// Checks the conflicting locks before running the statement.
+    pub preflight: Option<Preflight>,
+
+    /// The caller recorded in the history.
+    pub actor: Option<String>,
+}
+
+/// The preflight checks a table: rejected for an index or a whole schema.
+const PREFLIGHT_TABLES_ONLY: &str = "preflight supported for tables only";
+
+/// How the statement of each relation is run(see RunSettings).
+#[derive(Clone, Copy, Default, InputObject)]
+pub struct RunInput {
+    pub timeouts: Option<Timeouts>,
+    pub preflight: Option<Preflight>,
+}
+
+impl From<RunInput> for RunSettings {
+    fn from(input: RunInput) -> Self {
+        Self {
+            dry_run: false,
+            timeouts: input.timeouts,
+            preflight: input.preflight,
+            actor: None,
+        }
+    }
+}
+
+/// Creates the settings of the request(the caller from the Actor in the request data).
+fn run_settings(ctx: &Context<'_>, dry_run: Option<bool>, run: Option<RunInput>) -> RunSettings {
+    RunSettings {
+        dry_run: dry_run.unwrap_or_default(),
+        actor: ctx.data_opt::<Actor>().map(|a| a.0.clone()),
+        ..run.unwrap_or_default().into()
+    }
+}
+
+/// The connections of the pool left for the others(e.g, preflight, history, jobs) by a batch.
+pub const POOL_RESERVED_CONNECTIONS: u32 = 2;
+
+/// How a batch of tables is processed.
+#[derive(Clone)]
+pub struct BatchSettings {
+    pub keep_going: bool,
+    pub concurrency: usize,
+    pub run: RunSettings,
+}
+
 impl MutationRoot {
-    async fn analyze_by_table_name(&self, schema: String, name: String) -> Result<bool, io::Error> {
+    /// The connections of the pool usable by a batch(at least 1).
+    fn max_concurrency(&self) -> usize {
+        let size: u32 = self.az.pool.options().get_max_connections();
+        size.saturating_sub(POOL_RESERVED_CONNECTIONS).max(1) as usize
+    }
+
+    async fn estimated_bytes(&self, schema: &str, name: &str) -> Result<Option<i64>, io::Error> {
+        if SCHEMA_WIDE == name {
+            return Ok(Some(relation::schema_size(&self.az.pool, schema).await?));
+        }
+        let sizes: HashMap<String, i64> =
+            relation::relation_sizes(&self.az.pool, schema, &[name.into()]).await?;
+        Ok(sizes.get(name).copied())
+    }
+
+    /// Records the executed statements if the history is configured.
+    ///
+    /// The failures are added to the warnings of the results(not failing the statements).
+    async fn record(
+        &self,
+        operation: MaintenanceOperation,
+        results: &mut [MaintenanceResult],
+        actor: Option<&str>,
+    ) {
+        if let Some(h) = &self.history {
+            h.record_all(operation, results, None, actor).await;
+        }
+    }
+
+    /// Executes the statement with the requested(or configured) timeouts.
+    ///
+    /// Returns the result with the number of the attempts(retried on the transient failures).
+    async fn execute(
+        &self,
+        operation: MaintenanceOperation,
+        sql: &str,
+        requested: Option<Timeouts>,
+    ) -> (Result<(), io::Error>, i32) {
+        let timeouts: Timeouts = self.timeouts.timeouts(operation, requested);
+        self.retry
+            .run(|| async {
+                let res: Result<(), MaintenanceError> = match operation {
+                    MaintenanceOperation::Analyze => self.az.execute(sql, &timeouts).await,
+                    _ => timeout::execute_pooled(&self.az.pool, operation, sql, &timeouts).await,
+                };
+                Ok(res?)
+            })
+            .await
+    }
+
+    /// Checks the conflicting locks(the skipped result if the relation is skipped).
+    async fn preflight(
+        &self,
+        schema: &str,
+        name: &str,
+        lock: LockMode,
+        preflight: Option<Preflight>,
+    ) -> Result<Option<MaintenanceResult>, io::Error> {
+        let Some(pf) = preflight else {
+            return Ok(None);
+        };
+        match locks::preflight(&self.az.pool, schema, name, lock, &pf).await? {
+            PreflightOutcome::Clear => Ok(None),
+            PreflightOutcome::Skip(blockers) => Ok(Some(MaintenanceResult::locked(
+                schema.into(),
+                name.into(),
+                blockers,
+            ))),
+        }
+    }
+
+    /// Runs(or just returns on a dry run) the statement of a single relation.
+    async fn run_one(
+        &self,
+        operation: MaintenanceOperation,
+        schema: &str,
+        name: &str,
+        sql: String,
+        lock: LockMode,
+        settings: RunSettings,
+    ) -> Result<MaintenanceResult, io::Error> {
+        let estimated: Option<i64> = self.estimated_bytes(schema, name).await?;
+        if settings.dry_run {
+            return Ok(
+                MaintenanceResult::planned(schema.into(), name.into(), Ok(sql))
+                    .with_estimated_bytes(estimated),
+            );
+        }
+        self.windows.check(operation)?;
+        if let Some(skipped) = self
+            .preflight(schema, name, lock, settings.preflight)
+            .await?
+        {
+            return Ok(skipped.with_estimated_bytes(estimated));
+        }
+        let started_at = Utc::now();
+        let started = Instant::now();
+        let (res, attempts) = self.execute(operation, &sql, settings.timeouts).await;
+        let elapsed = started.elapsed();
+        let recorded: Result<String, io::Error> = match &res {
+            Ok(_) => Ok(sql),
+            Err(e) => Err(io::Error::other(e.to_string())),
+        };
+        let mut result =
+            MaintenanceResult::new(schema.into(), name.into(), started_at, elapsed, recorded)
+                .with_estimated_bytes(estimated)
+                .with_attempts(attempts);
+        let actor: Option<&str> = settings.actor.as_deref();
+        self.record(operation, std::slice::from_mut(&mut result), actor)
+            .await;
+        res.map(|_| result)
+    }
+
+    async fn check_table(&self, schema: &str, name: &str) -> Result<CheckedTableName, io::Error> {
         // TableNameChecker should reject unknown table "name"s
-        let unchecked = UncheckedTableName(name);
-        let checked: CheckedTableName = self.checker.check_table_name(&schema, unchecked).await?;
-        self.az.analyze(&checked).await?;
-        Ok(true)
+        let unchecked = UncheckedTableName(name.into());
+        Ok(self.checker.check_table_name(schema, unchecked).await?)
     }
 
-    async fn analyze_tables(&self, schema: String, names: Vec<String>) -> Result<bool, io::Error> {
-        for name in names {
-            // TableNameChecker should reject unknown table "name"s
-            let unchecked = UncheckedTableName(name);
-            let checked: CheckedTableName =
-                self.checker.check_table_name(&schema, unchecked).await?;
-            self.az.analyze(&checked).await?;
+    /// Creates the ANALYZE statement of the table(checked unless prechecked).
+    async fn analyze_one_sql(
+        &self,
+        schema: &str,
+        name: &str,
+        prechecked: Option<CheckedTableName>,
+        opts: &AnalyzeOptions,
+    ) -> Result<String, io::Error> {
+        let table: CheckedTableName = match prechecked {
+            Some(table) => table,
+            None => self.check_table(schema, name).await?,
+        };
+        Ok(self.az.analyze_sql(&table, &[], opts).await?)
+    }
+
+    async fn analyze_one(
+        &self,
+        schema: &str,
+        name: &str,
+        prechecked: Option<CheckedTableName>,
+        opts: &AnalyzeOptions,
+        timeouts: Option<Timeouts>,
+    ) -> (Result<String, io::Error>, i32) {
+        let sql: String = match self.analyze_one_sql(schema, name, prechecked, opts).await {
+            Ok(sql) => sql,
+            Err(e) => return (Err(e), 0),
+        };
+        let (res, attempts) = self
+            .execute(MaintenanceOperation::Analyze, &sql, timeouts)
+            .await;
+        (res.map(|_| sql), attempts)
+    }
+
+    /// Analyzes the tables(largest first if concurrent) in the given order.
+    ///
+    /// The tables not started before the maintenance window closes are skipped.
+    async fn analyze_batch(
+        &self,
+        schema: &str,
+        names: Vec<String>,
+        prechecked: Vec<Option<CheckedTableName>>,
+        opts: &AnalyzeOptions,
+        settings: BatchSettings,
+    ) -> Result<Vec<MaintenanceResult>, io::Error> {
+        let operation = MaintenanceOperation::Analyze;
+        let run: &RunSettings = &settings.run;
+        let keep_going: bool = settings.keep_going;
+        if !run.dry_run {
+            self.windows.check(operation)?;
+        }
+        let sizes: HashMap<String, i64> =
+            relation::relation_sizes(&self.az.pool, schema, &names).await?;
+
+        let mut tasks: Vec<(usize, String, Option<CheckedTableName>)> = names
+            .into_iter()
+            .zip(prechecked)
+            .enumerate()
+            .map(|(i, (name, pre))| (i, name, pre))
+            .collect();
+        if 1 < settings.concurrency {
+            // the semaphore is fair: tasks acquire permits in this order
+            tasks.sort_by_key(|(i, name, _)| {
+                let size: i64 = sizes.get(name).copied().unwrap_or_default();
+                (Reverse(size), *i)
+            });
+        }
+
+        let sem = Semaphore::new(settings.concurrency);
+        let failed = AtomicBool::new(false);
+        let futs = tasks.into_iter().map(|(i, name, pre)| {
+            let (sem, failed) = (&sem, &failed);
+            async move {
+                if run.dry_run {
+                    let res = self.analyze_one_sql(schema, &name, pre, opts).await;
+                    return (i, MaintenanceResult::planned(schema.into(), name, res));
+                }
+                let permit = sem.acquire().await;
+                if failed.load(Ordering::SeqCst) && !keep_going {
+                    return (i, MaintenanceResult::skipped(schema.into(), name));
+                }
+                if !self.windows.is_open(operation, Utc::now()) {
+                    let reason: String = WINDOW_CLOSED.into();
+                    return (
+                        i,
+                        MaintenanceResult::skipped_because(schema.into(), name, reason),
+                    );
+                }
+                let started_at = Utc::now();
+                let started = Instant::now();
+                let lock = LockMode::taken_by(operation, false);
+                let (res, attempts) = match permit {
+                    Ok(_permit) => match self.preflight(schema, &name, lock, run.preflight).await {
+                        Ok(Some(skipped)) => return (i, skipped),
+                        Ok(None) => {
+                            self.analyze_one(schema, &name, pre, opts, run.timeouts)
+                                .await
+                        }
+                        Err(e) => (Err(e), 0),
+                    },
+                    Err(e) => (Err(io::Error::other(e)), 0),
+                };
+                if res.is_err() {
+                    failed.store(true, Ordering::SeqCst);
+                }
+                let elapsed = started.elapsed();
+                let result = MaintenanceResult::new(schema.into(), name, started_at, elapsed, res)
+                    .with_attempts(attempts);
+                (i, result)
+            }
+        });
+
+        let mut results: Vec<(usize, MaintenanceResult)> = join_all(futs).await;
+        results.sort_by_key(|(i, _)| *i);
+        let mut results: Vec<MaintenanceResult> = results
+            .into_iter()
+            .map(|(_, r)| {
+                let estimated: Option<i64> = sizes.get(&r.table).copied();
+                r.with_estimated_bytes(estimated)
+            })
+            .collect();
+        self.record(operation, &mut results, run.actor.as_deref())
+            .await;
+        Ok(results)
+    }
+
+    /// Checks the batch at once unless validate_all is false.
+    async fn precheck_tables(
+        &self,
+        schema: &str,
+        names: &[String],
+        validate_all: bool,
+    ) -> Result<Vec<Option<CheckedTableName>>, io::Error> {
+        if !validate_all {
+            return Ok(names.iter().map(|_| None).collect());
         }
-        Ok(true)
+        // TableNameChecker should reject the batch if any of the "names" is unknown
+        let unchecked: Vec<UncheckedTableName> =
+            names.iter().cloned().map(UncheckedTableName).collect();
+        let checked: Vec<CheckedTableName> =
+            self.checker.check_table_names(schema, unchecked).await?;
+        Ok(checked.into_iter().map(Some).collect())
+    }
+}
+
+#[Object]
+impl MutationRoot {
+    /// Analyzes the table(or returns the statement on a dry run).
+    async fn analyze_by_table_name(
+        &self,
+        ctx: &Context<'_>,
+        schema: String,
+        name: String,
+        options: Option<AnalyzeOptions>,
+        dry_run: Option<bool>,
+        run: Option<RunInput>,
+    ) -> Result<MaintenanceResult, io::Error> {
+        let opts: AnalyzeOptions = options.unwrap_or_default();
+        let sql: String = self.analyze_one_sql(&schema, &name, None, &opts).await?;
+        let operation = MaintenanceOperation::Analyze;
+        let settings: RunSettings = run_settings(ctx, dry_run, run);
+        let lock = LockMode::taken_by(operation, false);
+        self.run_one(operation, &schema, &name, sql, lock, settings)
+            .await
+    }
+
+    /// Analyzes the columns of the table(at least one column required).
+    #[allow(clippy::too_many_arguments)]
+    async fn analyze_columns(
+        &self,
+        ctx: &Context<'_>,
+        schema: String,
+        name: String,
+        columns: Vec<String>,
+        options: Option<AnalyzeOptions>,
+        dry_run: Option<bool>,
+        run: Option<RunInput>,
+    ) -> Result<MaintenanceResult, io::Error> {
+        if columns.is_empty() {
+            // ANALYZE without the column list would analyze all columns
+            return Err(MaintenanceError::InvalidInput("no columns specified".into()).into());
+        }
+        let opts: AnalyzeOptions = options.unwrap_or_default();
+        let checked: CheckedTableName = self.check_table(&schema, &name).await?;
+        let mut cols: Vec<CheckedColumnName> = Vec::with_capacity(columns.len());
+        for column in columns {
+            // ColumnNameChecker should reject unknown column names
+            let unchecked = UncheckedColumnName(column);
+            cols.push(
+                self.col_checker
+                    .check_column_name(&checked, unchecked)
+                    .await?,
+            );
+        }
+        let sql: String = self.az.analyze_sql(&checked, &cols, &opts).await?;
+        let operation = MaintenanceOperation::Analyze;
+        let settings: RunSettings = run_settings(ctx, dry_run, run);
+        let lock = LockMode::taken_by(operation, false);
+        self.run_one(operation, &schema, &name, sql, lock, settings)
+            .await
+    }
+
+    /// Analyzes the tables and reports the result of each table in the given order.
+    ///
+    /// All names are validated before any ANALYZE unless validateAll is false.
+    /// Tables are analyzed one by one unless maxConcurrency is greater than 1,
+    /// in which case the largest tables are started first.
+    /// maxConcurrency is limited by the pool size(some connections are reserved for the others).
+    #[allow(clippy::too_many_arguments)]
+    async fn analyze_tables(
+        &self,
+        ctx: &Context<'_>,
+        schema: String,
+        names: Vec<String>,
+        options: Option<AnalyzeOptions>,
+        continue_on_error: Option<bool>,
+        validate_all: Option<bool>,
+        max_concurrency: Option<i32>,
+        dry_run: Option<bool>,
+        run: Option<RunInput>,
+    ) -> Result<Vec<MaintenanceResult>, io::Error> {
+        let opts: AnalyzeOptions = options.unwrap_or_default();
+        let limit: usize = self.max_concurrency();
+        let concurrency: usize = match max_concurrency.unwrap_or(1) {
+            i if 0 < i && (i as usize) <= limit => i as usize,
+            i => {
+                return Err(MaintenanceError::InvalidInput(format!(
+                    "invalid max concurrency: {i}(1 - {limit})"
+                ))
+                .into());
+            }
+        };
+        let settings = BatchSettings {
+            keep_going: continue_on_error.unwrap_or_default(),
+            concurrency,
+            run: run_settings(ctx, dry_run, run),
+        };
+
+        let prechecked: Vec<Option<CheckedTableName>> = self
+            .precheck_tables(&schema, &names, validate_all.unwrap_or(true))
+            .await?;
+
+        self.analyze_batch(&schema, names, prechecked, &opts, settings)
+            .await
+    }
+
+    /// Analyzes the tables whose modified rows exceed the autovacuum analyze threshold.
+    async fn analyze_stale_tables(
+        &self,
+        ctx: &Context<'_>,
+        schema: Option<String>,
+        pattern: Option<String>,
+        options: Option<AnalyzeOptions>,
+        dry_run: Option<bool>,
+        run: Option<RunInput>,
+    ) -> Result<Vec<MaintenanceResult>, io::Error> {
+        let opts: AnalyzeOptions = options.unwrap_or_default();
+        let schema: String = schema.unwrap_or_else(|| "public".into());
+        let pattern: String = pattern.unwrap_or_else(|| "%".into());
+        let stale: Vec<StaleTable> = stale::stale_tables(&self.az.pool, &schema, &pattern).await?;
+        let names: Vec<String> = stale.into_iter().map(|s| s.table).collect();
+        // the stale tables are discovered: the forbidden ones are just excluded
+        let names: Vec<String> = self.policy.allowed_names(&schema, names);
+
+        let prechecked: Vec<Option<CheckedTableName>> =
+            self.precheck_tables(&schema, &names, true).await?;
+        let settings = BatchSettings {
+            keep_going: true,
+            concurrency: 1,
+            run: run_settings(ctx, dry_run, run),
+        };
+        self.analyze_batch(&schema, names, prechecked, &opts, settings)
+            .await
+    }
+
+    async fn vacuum_by_table_name(
+        &self,
+        ctx: &Context<'_>,
+        schema: String,
+        name: String,
+        options: Option<VacuumOptions>,
+        dry_run: Option<bool>,
+        run: Option<RunInput>,
+    ) -> Result<MaintenanceResult, io::Error> {
+        let opts: VacuumOptions = options.unwrap_or_default();
+        let checked: CheckedTableName = self.check_table(&schema, &name).await?;
+        let sql: String = self.vc.vacuum_sql(&checked, &opts).await?;
+        let operation = MaintenanceOperation::Vacuum;
+        let settings: RunSettings = run_settings(ctx, dry_run, run);
+        let lock = LockMode::taken_by(operation, opts.full.unwrap_or_default());
+        self.run_one(operation, &schema, &name, sql, lock, settings)
+            .await
+    }
+
+    /// Vacuums the tables one by one.
+    ///
+    /// The rest are skipped after the window closed(or a failure unless continueOnError).
+    #[allow(clippy::too_many_arguments)]
+    async fn vacuum_tables(
+        &self,
+        ctx: &Context<'_>,
+        schema: String,
+        names: Vec<String>,
+        options: Option<VacuumOptions>,
+        continue_on_error: Option<bool>,
+        dry_run: Option<bool>,
+        run: Option<RunInput>,
+    ) -> Result<Vec<MaintenanceResult>, io::Error> {
+        let opts: VacuumOptions = options.unwrap_or_default();
+        let keep_going: bool = continue_on_error.unwrap_or_default();
+        let RunSettings {
+            dry_run,
+            timeouts,
+            preflight,
+            actor,
+        } = run_settings(ctx, dry_run, run);
+        let operation = MaintenanceOperation::Vacuum;
+        let lock = LockMode::taken_by(operation, opts.full.unwrap_or_default());
+        if !dry_run {
+            self.windows.check(operation)?;
+        }
+        let checked: Vec<Option<CheckedTableName>> =
+            self.precheck_tables(&schema, &names, true).await?;
+        let sizes: HashMap<String, i64> =
+            relation::relation_sizes(&self.az.pool, &schema, &names).await?;
+
+        let mut results: Vec<MaintenanceResult> = Vec::with_capacity(names.len());
+        let mut failed: bool = false;
+        for (name, table) in names.into_iter().zip(checked.into_iter().flatten()) {
+            let estimated: Option<i64> = sizes.get(&name).copied();
+            if failed {
+                results.push(MaintenanceResult::skipped(schema.clone(), name));
+                continue;
+            }
+            let sql: Result<String, io::Error> = self.vc.vacuum_sql(&table, &opts).await;
+            if dry_run {
+                let planned = MaintenanceResult::planned(schema.clone(), name, sql);
+                results.push(planned.with_estimated_bytes(estimated));
+                continue;
+            }
+            if !self.windows.is_open(operation, Utc::now()) {
+                let reason: String = WINDOW_CLOSED.into();
+                results.push(MaintenanceResult::skipped_because(
+                    schema.clone(),
+                    name,
+                    reason,
+                ));
+                continue;
+            }
+            let started_at = Utc::now();
+            let started = Instant::now();
+            let checked: Result<Option<MaintenanceResult>, io::Error> =
+                self.preflight(&schema, &name, lock, preflight).await;
+            let (res, attempts) = match (sql, checked) {
+                (_, Ok(Some(skipped))) => {
+                    results.push(skipped.with_estimated_bytes(estimated));
+                    continue;
+                }
+                (Ok(sql), Ok(None)) => {
+                    let (res, attempts) = self.execute(operation, &sql, timeouts).await;
+                    (res.map(|_| sql), attempts)
+                }
+                (Err(e), _) | (_, Err(e)) => (Err(e), 0),
+            };
+            failed |= res.is_err() && !keep_going;
+            let elapsed = started.elapsed();
+            let result = MaintenanceResult::new(schema.clone(), name, started_at, elapsed, res)
+                .with_attempts(attempts);
+            results.push(result.with_estimated_bytes(estimated));
+        }
+        if !dry_run {
+            self.record(operation, &mut results, actor.as_deref()).await;
+        }
+        Ok(results)
+    }
+
+    /// Rebuilds the index(concurrently by default).
+    async fn reindex_index(
+        &self,
+        ctx: &Context<'_>,
+        schema: String,
+        name: String,
+        concurrently: Option<bool>,
+        dry_run: Option<bool>,
+        run: Option<RunInput>,
+    ) -> Result<MaintenanceResult, io::Error> {
+        self.policy.check_schema(&schema)?;
+        // IndexNameChecker should reject unknown index "name"s
+        let unchecked = UncheckedIndexName(name);
+        let checked: CheckedIndexName = self
+            .idx_checker
+            .check_index_name(&schema, unchecked)
+            .await?;
+        // the policy restricts the tables(not the indexes)
+        self.policy.check(&schema, checked.table())?;
+        let sql: String = self
+            .ri
+            .reindex_index_sql(&checked, concurrently.unwrap_or(true))
+            .await?;
+        let operation = MaintenanceOperation::Reindex;
+        let settings: RunSettings = run_settings(ctx, dry_run, run);
+        if settings.preflight.is_some() {
+            return Err(MaintenanceError::InvalidInput(PREFLIGHT_TABLES_ONLY.into()).into());
+        }
+        let lock = LockMode::taken_by(operation, !concurrently.unwrap_or(true));
+        self.run_one(operation, &schema, checked.as_str(), sql, lock, settings)
+            .await
+    }
+
+    /// Rebuilds all indexes of the table(concurrently by default).
+    async fn reindex_table(
+        &self,
+        ctx: &Context<'_>,
+        schema: String,
+        name: String,
+        concurrently: Option<bool>,
+        dry_run: Option<bool>,
+        run: Option<RunInput>,
+    ) -> Result<MaintenanceResult, io::Error> {
+        let checked: CheckedTableName = self.check_table(&schema, &name).await?;
+        let concurrently: bool = concurrently.unwrap_or(true);
+        let sql: String = self.ri.reindex_table_sql(&checked, concurrently).await?;
+        let operation = MaintenanceOperation::Reindex;
+        let settings: RunSettings = run_settings(ctx, dry_run, run);
+        let lock = LockMode::taken_by(operation, !concurrently);
+        self.run_one(operation, &schema, &name, sql, lock, settings)
+            .await
+    }
+
+    /// Rebuilds all indexes in the schema(concurrently by default).
+    async fn reindex_schema(
+        &self,
+        ctx: &Context<'_>,
+        schema: String,
+        concurrently: Option<bool>,
+        dry_run: Option<bool>,
+        run: Option<RunInput>,
+    ) -> Result<MaintenanceResult, io::Error> {
+        self.policy.check_schema_wide(&schema)?;
+        let sql: String = self
+            .ri
+            .reindex_schema_sql(&schema, concurrently.unwrap_or(true))
+            .await?;
+        let operation = MaintenanceOperation::Reindex;
+        let settings: RunSettings = run_settings(ctx, dry_run, run);
+        if settings.preflight.is_some() {
+            return Err(MaintenanceError::InvalidInput(PREFLIGHT_TABLES_ONLY.into()).into());
+        }
+        let lock = LockMode::taken_by(operation, !concurrently.unwrap_or(true));
+        self.run_one(operation, &schema, SCHEMA_WIDE, sql, lock, settings)
+            .await
+    }
+
+    async fn refresh_materialized_view(
+        &self,
+        ctx: &Context<'_>,
+        schema: String,
+        name: String,
+        concurrently: Option<bool>,
+        dry_run: Option<bool>,
+        run: Option<RunInput>,
+    ) -> Result<MatViewRefresh, io::Error> {
+        self.policy.check(&schema, &name)?;
+        // MatViewNameChecker should reject unknown view "name"s
+        let unchecked = UncheckedMatViewName(name);
+        let checked: CheckedMatViewName = self
+            .mv_checker
+            .check_matview_name(&schema, unchecked)
+            .await?;
+        let concurrently: bool = concurrently.unwrap_or_default();
+        let planned: MatViewRefresh = self.rf.refresh_sql(&checked, concurrently).await?;
+        let settings: RunSettings = run_settings(ctx, dry_run, run);
+        if settings.dry_run {
+            let estimated: Option<i64> = self.estimated_bytes(&schema, checked.as_str()).await?;
+            return Ok(MatViewRefresh {
+                estimated_bytes: estimated,
+                ..planned
+            });
+        }
+        let operation = MaintenanceOperation::RefreshMaterializedView;
+        let lock = LockMode::taken_by(operation, !concurrently);
+        let result: MaintenanceResult = self
+            .run_one(
+                operation,
+                &schema,
+                checked.as_str(),
+                planned.sql.clone(),
+                lock,
+                settings,
+            )
+            .await?;
+        Ok(MatViewRefresh {
+            refreshed: result.status.is_executed(),
+            estimated_bytes: result.estimated_bytes,
+            ..planned
+        })
+    }
+
+    /// Validates the tables and queues them to be analyzed by the job workers.
+    async fn submit_analyze_job(
+        &self,
+        ctx: &Context<'_>,
+        schema: String,
+        names: Vec<String>,
+        options: Option<AnalyzeOptions>,
+        continue_on_error: Option<bool>,
+        run: Option<RunInput>,
+    ) -> Result<Job, io::Error> {
+        let settings: RunSettings = run_settings(ctx, None, run);
+        let checked: Vec<CheckedTableName> = self
+            .precheck_tables(&schema, &names, true)
+            .await?
+            .into_iter()
+            .flatten()
+            .collect();
+        let task = JobTask::Analyze(options.unwrap_or_default());
+        self.jobs.submit(
+            schema,
+            checked,
+            task,
+            continue_on_error.unwrap_or_default(),
+            settings,
+        )
+    }
+
+    /// Stops the job: cancels the running statement(or terminates its backend).
+    async fn cancel_job(&self, id: i64, terminate: Option<bool>) -> Result<Job, io::Error> {
+        self.jobs.cancel(id, terminate.unwrap_or_default()).await
+    }
+
+    /// Creates a schedule which submits a job of the matching tables when due.
+    async fn create_schedule(&self, input: ScheduleInput) -> Result<Schedule, io::Error> {
+        self.scheduler.create(input)
+    }
+
+    async fn delete_schedule(&self, id: i64) -> Result<Schedule, io::Error> {
+        self.scheduler.delete(id).ok_or_else(|| {
+            MaintenanceError::NotFound(format!("the schedule {id} not found")).into()
+        })
+    }
+
+    /// Validates the tables and queues them to be vacuumed by the job workers.
+    async fn submit_vacuum_job(
+        &self,
+        ctx: &Context<'_>,
+        schema: String,
+        names: Vec<String>,
+        options: Option<VacuumOptions>,
+        continue_on_error: Option<bool>,
+        run: Option<RunInput>,
+    ) -> Result<Job, io::Error> {
+        let settings: RunSettings = run_settings(ctx, None, run);
+        let checked: Vec<CheckedTableName> = self
+            .precheck_tables(&schema, &names, true)
+            .await?
+            .into_iter()
+            .flatten()
+            .collect();
+        let task = JobTask::Vacuum(options.unwrap_or_default());
+        self.jobs.submit(
+            schema,
+            checked,
+            task,
+            continue_on_error.unwrap_or_default(),
+            settings,
+        )
     }
 }
 
 pub struct PgQuery {
     pub pool: PgPool,
+    pub loader: DataLoader<TableExistsLoader>,
+    pub jobs: Arc<JobManager>,
+    pub scheduler: Arc<Scheduler>,
+    pub history: Option<Arc<PgHistory>>,
+    pub windows: Arc<WindowPolicy>,
+    version: Arc<ServerVersion>,
+}
+
+impl PgQuery {
+    pub fn new_default(p: &PgPool, jobs: Arc<JobManager>, scheduler: Arc<Scheduler>) -> Self {
+        let chk = PgTabChk { pool: p.clone() };
+        Self {
+            pool: p.clone(),
+            loader: TableExistsLoader::new_loader(Arc::new(chk)),
+            jobs,
+            scheduler,
+            history: None,
+            windows: Arc::new(WindowPolicy::default()),
+            version: Arc::new(ServerVersion::default()),
+        }
+    }
 }
 
 #[Object]
 impl PgQuery {
+    pub async fn job(&self, id: i64) -> Option<Job> {
+        self.jobs.job(id)
+    }
+
+    /// Gets the jobs(all if the status is not specified).
+    pub async fn jobs(&self, status: Option<JobStatus>) -> Vec<Job> {
+        self.jobs.jobs(status)
+    }
+
+    /// Checks if the operation is allowed now and when its window opens or closes.
+    pub async fn maintenance_window(&self, operation: MaintenanceOperation) -> WindowState {
+        self.windows.state(operation, Utc::now())
+    }
+
+    pub async fn schedule(&self, id: i64) -> Option<Schedule> {
+        self.scheduler.schedule(id)
+    }
+
+    /// Gets the schedules with their next and last runs.
+    pub async fn schedules(&self) -> Vec<Schedule> {
+        self.scheduler.schedules()
+    }
+
+    /// Gets the recorded maintenance(latest first) if the history is configured.
+    pub async fn maintenance_history(
+        &self,
+        table: Option<String>,
+        since: Option<DateTime<Utc>>,
+        limit: Option<i64>,
+    ) -> Result<Vec<HistoryEntry>, io::Error> {
+        let history: &PgHistory = self.history.as_deref().ok_or_else(|| {
+            MaintenanceError::Unsupported("the maintenance history is not configured".into())
+        })?;
+        history
+            .entries(
+                table.as_deref(),
+                since,
+                limit.unwrap_or(HISTORY_LIMIT_DEFAULT),
+            )
+            .await
+    }
+
+    /// Gets the tables which need ANALYZE(most stale first).
+    pub async fn stale_tables(
+        &self,
+        schema: Option<String>,
+        pattern: Option<String>,
+    ) -> Result<Vec<StaleTable>, io::Error> {
+        stale::stale_tables(
+            &self.pool,
+            &schema.unwrap_or_else(|| "public".into()),
+            &pattern.unwrap_or_else(|| "%".into()),
+        )
+        .await
+    }
+
+    /// Gets the statistics of the tables(e.g, modified rows since the last analyze).
+    pub async fn table_stats(
+        &self,
+        schema: Option<String>,
+        pattern: Option<String>,
+    ) -> Result<Vec<TableStats>, io::Error> {
+        stats::table_stats(
+            &self.pool,
+            &self.version,
+            &schema.unwrap_or_else(|| "public".into()),
+            &pattern.unwrap_or_else(|| "%".into()),
+        )
+        .await
+    }
+
+    /// Gets the other sessions locking the relation(oldest transaction first).
+    ///
+    /// The conflicts are computed against the lockMode(SHARE_UPDATE_EXCLUSIVE: ANALYZE, VACUUM).
+    pub async fn blockers(
+        &self,
+        schema: String,
+        table: String,
+        lock_mode: Option<LockMode>,
+    ) -> Result<Vec<Blocker>, io::Error> {
+        let mode: LockMode = lock_mode.unwrap_or(LockMode::ShareUpdateExclusive);
+        locks::blockers(&self.pool, &schema, &table, mode).await
+    }
+
+    /// Checks if the table exists(batched with other tableExists fields).
+    pub async fn table_exists(
+        &self,
+        schema: Option<String>,
+        name: String,
+    ) -> Result<bool, io::Error> {
+        let key = TableKey {
+            schema: schema.unwrap_or_else(|| "public".into()),
+            name,
+        };
+        let found: Option<bool> = self
+            .loader
+            .load_one(key)
+            .await
+            .map_err(|e| io::Error::from(MaintenanceError::clone(&e)))?;
+        Ok(found.unwrap_or_default())
+    }
+
     pub async fn get_table_names(
         &self,
         schema: Option<String>,
@@ -170,16 +1616,131 @@ impl PgQuery {
     }
 }
 
-pub type PgSchema = Schema<PgQuery, MutationRoot, EmptySubscription>;
+pub struct SubscriptionRoot {
+    pub pool: PgPool,
+    pub jobs: Arc<JobManager>,
+    version: Arc<ServerVersion>,
+}
+
+impl SubscriptionRoot {
+    pub fn new(p: &PgPool, jobs: Arc<JobManager>) -> Self {
+        Self {
+            pool: p.clone(),
+            jobs,
+            version: Arc::new(ServerVersion::default()),
+        }
+    }
+}
+
+#[Subscription]
+impl SubscriptionRoot {
+    /// Streams the progress of the running maintenance every intervalMs.
+    ///
+    /// The stream ends after the job finishes if jobId is specified.
+    /// All maintenance in the database is reported if neither jobId nor pid is specified.
+    async fn maintenance_progress(
+        &self,
+        job_id: Option<i64>,
+        pid: Option<i32>,
+        interval_ms: Option<i32>,
+    ) -> Result<impl Stream<Item = async_graphql::Result<Vec<MaintenanceProgress>>>, io::Error>
+    {
+        let interval: Duration = match interval_ms {
+            None => progress::PROGRESS_INTERVAL_DEFAULT,
+            Some(ms) if 0 < ms => Duration::from_millis(ms as u64),
+            Some(ms) => {
+                return Err(
+                    MaintenanceError::InvalidInput(format!("invalid interval: {ms}")).into(),
+                );
+            }
+        };
+        let polled = progress::progress_stream(
+            self.pool.clone(),
+            self.version.clone(),
+            self.jobs.clone(),
+            job_id,
+            pid,
+            interval,
+        );
+        Ok(polled.map(|r| r.map_err(|e| MaintenanceError::from(e).extend())))
+    }
+}
+
+pub type PgSchema = Schema<PgQuery, MutationRoot, SubscriptionRoot>;
 
-pub fn schema_new(q: PgQuery, m: MutationRoot) -> PgSchema {
-    Schema::build(q, m, EmptySubscription).finish()
+pub fn schema_new(q: PgQuery, m: MutationRoot, s: SubscriptionRoot) -> PgSchema {
+    Schema::build(q, m, s).extension(ErrorCodes).finish()
+}
+
+fn new_scheduler(p: &PgPool, jobs: Arc<JobManager>, policy: Arc<TablePolicy>) -> Arc<Scheduler> {
+    let chk = PgTabChk { pool: p.clone() };
+    let checker = PolicyChecker {
+        inner: chk,
+        policy: policy.clone(),
+    };
+    Scheduler::start(p, Box::new(checker), policy, jobs)
 }
 
 pub fn schema_new_default(p: &PgPool) -> PgSchema {
-    let pg_query = PgQuery { pool: p.clone() };
-    let mutation_root = MutationRoot::new_default(p);
-    schema_new(pg_query, mutation_root)
+    let jobs: Arc<JobManager> = JobManager::start(
+        JobRunner::new_default(p),
+        JOB_WORKERS_DEFAULT,
+        JobRetention::default(),
+    );
+    let policy = Arc::new(TablePolicy::default());
+    let scheduler: Arc<Scheduler> = new_scheduler(p, jobs.clone(), policy);
+    let pg_query = PgQuery::new_default(p, jobs.clone(), scheduler.clone());
+    let mutation_root = MutationRoot::new_default(p, jobs.clone(), scheduler);
+    let subscription_root = SubscriptionRoot::new(p, jobs);
+    schema_new(pg_query, mutation_root, subscription_root)
+}
+
+/// Creates the schema(and the history table if configured).
+pub async fn schema_new_with_config(
+    p: &PgPool,
+    cfg: &MaintenanceConfig,
+) -> Result<PgSchema, io::Error> {
+    let history: Option<Arc<PgHistory>> = match &cfg.history {
+        None => None,
+        Some(h) => {
+            let history = PgHistory {
+                pool: p.clone(),
+                config: h.clone(),
+            };
+            history.migrate().await?;
+            Some(Arc::new(history))
+        }
+    };
+    let windows: Arc<WindowPolicy> = Arc::new(cfg.windows.clone());
+    let timeouts: Arc<TimeoutPolicy> = Arc::new(cfg.timeouts.clone());
+    let retry: Arc<RetryPolicy> = Arc::new(cfg.retry.clone());
+    let runner = JobRunner {
+        history: history.clone(),
+        windows: windows.clone(),
+        timeouts: timeouts.clone(),
+        retry: retry.clone(),
+        ..JobRunner::new_default(p)
+    };
+    let jobs: Arc<JobManager> = JobManager::start(runner, cfg.job_workers, cfg.job_retention);
+    let policy: Arc<TablePolicy> = Arc::new(cfg.policy.clone());
+    let scheduler: Arc<Scheduler> = new_scheduler(p, jobs.clone(), policy.clone());
+    for input in &cfg.schedules {
+        scheduler.create(input.clone())?;
+    }
+    let pg_query = PgQuery {
+        history: history.clone(),
+        windows: windows.clone(),
+        ..PgQuery::new_default(p, jobs.clone(), scheduler.clone())
+    };
+    let mutation_root = MutationRoot {
+        history,
+        windows,
+        timeouts,
+        retry,
+        ..MutationRoot::new_with_policy(p, jobs.clone(), scheduler, policy)
+    };
+    let subscription_root = SubscriptionRoot::new(p, jobs);
+    Ok(schema_new(pg_query, mutation_root, subscription_root))
 }
 
 pub async fn conn2pool(conn_str: &str) -> Result<PgPool, io::Error> {
@@ -190,3 +1751,120 @@ pub async fn conn2schema(conn_str: &str) -> Result<PgSchema, io::Error> {
     let pool = conn2pool(conn_str).await?;
     Ok(schema_new_default(&pool))
 }
+
+pub async fn conn2schema_with_config(
+    conn_str: &str,
+    cfg: &MaintenanceConfig,
+) -> Result<PgSchema, io::Error> {
+    let pool = conn2pool(conn_str).await?;
+    schema_new_with_config(&pool, cfg).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PG15: i32 = 150004;
+    const PG16: i32 = 160002;
+
+    #[test]
+    fn valid_buffer_sizes() {
+        for size in ["1024", "256kB", "16MB", "1GB", "1TB"] {
+            assert!(valid_buffer_size(size), "{size}");
+        }
+        for size in ["", "kB", "16 MB", "16mb", "-1", "1.5MB", "16MB'; --"] {
+            assert!(!valid_buffer_size(size), "{size}");
+        }
+    }
+
+    #[test]
+    fn to_sql_empty_without_options() {
+        let opts = AnalyzeOptions::default();
+        assert_eq!(opts.to_sql(PG15).ok(), Some("".into()));
+    }
+
+    #[test]
+    fn to_sql_lists_options() {
+        let opts = AnalyzeOptions {
+            verbose: Some(true),
+            skip_locked: Some(true),
+            buffer_usage_limit: Some("16MB".into()),
+            ..Default::default()
+        };
+        assert_eq!(
+            opts.to_sql(PG16).ok(),
+            Some("(VERBOSE, SKIP_LOCKED, BUFFER_USAGE_LIMIT '16MB')".into()),
+        );
+    }
+
+    #[test]
+    fn to_sql_gated_by_version() {
+        let skip_locked = AnalyzeOptions {
+            skip_locked: Some(true),
+            ..Default::default()
+        };
+        assert_eq!(
+            skip_locked.to_sql(PG_VERSION_SKIP_LOCKED).ok(),
+            Some("(SKIP_LOCKED)".into())
+        );
+        assert_eq!(
+            skip_locked.to_sql(110000).err().map(|e| e.code()),
+            Some("UNSUPPORTED"),
+        );
+
+        let limited = AnalyzeOptions {
+            buffer_usage_limit: Some("256kB".into()),
+            ..Default::default()
+        };
+        assert_eq!(
+            limited.to_sql(PG15).err().map(|e| e.code()),
+            Some("UNSUPPORTED")
+        );
+        assert!(limited.to_sql(PG_VERSION_BUFFER_USAGE_LIMIT).is_ok());
+    }
+
+    #[test]
+    fn to_sql_rejects_invalid_buffer_size() {
+        let opts = AnalyzeOptions {
+            buffer_usage_limit: Some("16MB'; --".into()),
+            ..Default::default()
+        };
+        assert_eq!(
+            opts.to_sql(PG16).err().map(|e| e.code()),
+            Some("INVALID_INPUT")
+        );
+    }
+
+    fn table(schema: &str, name: &str) -> CheckedTableName {
+        CheckedTableName::from_kind(
+            schema,
+            UncheckedTableName(name.into()),
+            Some(RelKind::Table),
+        )
+        .expect("table")
+    }
+
+    #[test]
+    fn quote_ident_escapes_quotes() {
+        assert_eq!(quote_ident("tab"), r#""tab""#);
+        assert_eq!(quote_ident(r#"my"tab"#), r#""my""tab""#);
+        assert_eq!(quote_ident(r#"""#), r#""""""#);
+        assert_eq!(quote_ident(""), r#""""#);
+    }
+
+    #[test]
+    fn qualified_keeps_case() {
+        assert_eq!(table("public", "MyTab").qualified(), r#""public"."MyTab""#);
+        assert_eq!(table("S1", "mytab").qualified(), r#""S1"."mytab""#);
+    }
+
+    #[test]
+    fn qualified_quotes_separators() {
+        assert_eq!(table("s1", "a.b").qualified(), r#""s1"."a.b""#);
+        assert_eq!(
+            table("s1", r#"t"; DROP TABLE x; --"#).qualified(),
+            r#""s1"."t""; DROP TABLE x; --""#,
+        );
+        assert_eq!(table("a.b", "c").qualified(), r#""a.b"."c""#);
+    }
+}
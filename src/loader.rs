@@ -0,0 +1,57 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use async_graphql::dataloader::DataLoader;
+use async_graphql::dataloader::Loader;
+
+use crate::TableChecker;
+use crate::error::MaintenanceError;
+
+#[derive(Clone, PartialEq, Eq, Hash)]
+pub struct TableKey {
+    pub schema: String,
+    pub name: String,
+}
+
+/// Loads the existence of the tables using a catalog query per schema.
+pub struct TableExistsLoader {
+    pub checker: Arc<dyn TableChecker>,
+}
+
+impl TableExistsLoader {
+    pub fn new_loader(checker: Arc<dyn TableChecker>) -> DataLoader<Self> {
+        DataLoader::new(Self { checker }, tokio::spawn)
+    }
+}
+
+impl Loader<TableKey> for TableExistsLoader {
+    type Value = bool;
+    type Error = Arc<MaintenanceError>;
+
+    async fn load(&self, keys: &[TableKey]) -> Result<HashMap<TableKey, bool>, Self::Error> {
+        let mut by_schema: HashMap<&str, Vec<String>> = HashMap::new();
+        for key in keys {
+            by_schema
+                .entry(&key.schema)
+                .or_default()
+                .push(key.name.clone());
+        }
+
+        let mut found: HashMap<TableKey, bool> = HashMap::with_capacity(keys.len());
+        for (schema, names) in by_schema {
+            let exists: HashMap<String, bool> = self
+                .checker
+                .tables_exist(schema, &names)
+                .await
+                .map_err(Arc::new)?;
+            for (name, b) in exists {
+                let key = TableKey {
+                    schema: schema.into(),
+                    name,
+                };
+                found.insert(key, b);
+            }
+        }
+        Ok(found)
+    }
+}
@@ -0,0 +1,383 @@
+use std::io;
+use std::time::Duration;
+
+use chrono::DateTime;
+use chrono::Utc;
+
+use sqlx::PgPool;
+
+use async_graphql::Enum;
+use async_graphql::InputObject;
+use async_graphql::SimpleObject;
+
+use crate::batch::MaintenanceOperation;
+use crate::error::MaintenanceError;
+
+/// The interval to check if the blockers released the relation.
+pub const LOCK_POLL_INTERVAL: Duration = Duration::from_millis(200);
+pub const LOCK_WAIT_DEFAULT: Duration = Duration::from_secs(10);
+
+/// The table-level lock modes(e.g, ANALYZE takes ShareUpdateExclusiveLock).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Enum)]
+pub enum LockMode {
+    AccessShare,
+    RowShare,
+    RowExclusive,
+    ShareUpdateExclusive,
+    Share,
+    ShareRowExclusive,
+    Exclusive,
+    AccessExclusive,
+}
+
+impl LockMode {
+    /// The lock taken on the table by the operation.
+    ///
+    /// exclusive: VACUUM FULL, or REINDEX(REFRESH) without CONCURRENTLY.
+    pub fn taken_by(operation: MaintenanceOperation, exclusive: bool) -> Self {
+        match (operation, exclusive) {
+            (MaintenanceOperation::Analyze, _) => Self::ShareUpdateExclusive,
+            (MaintenanceOperation::Vacuum, false) => Self::ShareUpdateExclusive,
+            (MaintenanceOperation::Vacuum, true) => Self::AccessExclusive,
+            (MaintenanceOperation::Reindex, false) => Self::ShareUpdateExclusive,
+            (MaintenanceOperation::Reindex, true) => Self::Share,
+            (MaintenanceOperation::RefreshMaterializedView, false) => Self::Exclusive,
+            (MaintenanceOperation::RefreshMaterializedView, true) => Self::AccessExclusive,
+        }
+    }
+
+    /// Parses pg_locks.mode(e.g, "AccessShareLock").
+    pub fn from_pg(mode: &str) -> Option<Self> {
+        match mode {
+            "AccessShareLock" => Some(Self::AccessShare),
+            "RowShareLock" => Some(Self::RowShare),
+            "RowExclusiveLock" => Some(Self::RowExclusive),
+            "ShareUpdateExclusiveLock" => Some(Self::ShareUpdateExclusive),
+            "ShareLock" => Some(Self::Share),
+            "ShareRowExclusiveLock" => Some(Self::ShareRowExclusive),
+            "ExclusiveLock" => Some(Self::Exclusive),
+            "AccessExclusiveLock" => Some(Self::AccessExclusive),
+            _ => None,
+        }
+    }
+
+    /// The conflict table of the documentation("Conflicting Lock Modes").
+    pub fn conflicts_with(&self, other: Self) -> bool {
+        use LockMode::*;
+        let conflicting: &[LockMode] = match self {
+            AccessShare => &[AccessExclusive],
+            RowShare => &[Exclusive, AccessExclusive],
+            RowExclusive => &[Share, ShareRowExclusive, Exclusive, AccessExclusive],
+            ShareUpdateExclusive => &[
+                ShareUpdateExclusive,
+                Share,
+                ShareRowExclusive,
+                Exclusive,
+                AccessExclusive,
+            ],
+            Share => &[
+                RowExclusive,
+                ShareUpdateExclusive,
+                ShareRowExclusive,
+                Exclusive,
+                AccessExclusive,
+            ],
+            ShareRowExclusive => &[
+                RowExclusive,
+                ShareUpdateExclusive,
+                Share,
+                ShareRowExclusive,
+                Exclusive,
+                AccessExclusive,
+            ],
+            Exclusive => &[
+                RowShare,
+                RowExclusive,
+                ShareUpdateExclusive,
+                Share,
+                ShareRowExclusive,
+                Exclusive,
+                AccessExclusive,
+            ],
+            AccessExclusive => &[
+                AccessShare,
+                RowShare,
+                RowExclusive,
+                ShareUpdateExclusive,
+                Share,
+                ShareRowExclusive,
+                Exclusive,
+                AccessExclusive,
+            ],
+        };
+        conflicting.contains(&other)
+    }
+}
+
+/// A session holding(or waiting for) a lock on the relation.
+#[derive(Debug, Clone, SimpleObject)]
+pub struct Blocker {
+    /// None for a prepared transaction(see gid).
+    pub pid: Option<i32>,
+
+    /// The global id of the prepared transaction holding the lock.
+    pub gid: Option<String>,
+
+    /// The lock mode(e.g, AccessExclusiveLock).
+    pub mode: String,
+
+    /// false if the session is waiting for the lock(queued ahead of the maintenance).
+    pub granted: bool,
+
+    /// true if the lock conflicts with the lock the maintenance takes.
+    pub conflicts: bool,
+
+    pub usename: Option<String>,
+    pub application_name: Option<String>,
+    pub state: Option<String>,
+    pub query: Option<String>,
+    pub xact_start: Option<DateTime<Utc>>,
+    pub query_start: Option<DateTime<Utc>>,
+
+    /// The age of the transaction holding the lock.
+    pub xact_age_ms: Option<i64>,
+}
+
+/// Gets the other sessions locking the relation(oldest transaction first).
+pub async fn blockers(
+    p: &PgPool,
+    schema: &str,
+    table: &str,
+    mode: LockMode,
+) -> Result<Vec<Blocker>, io::Error> {
+    let rows = sqlx::query!(
+        r#"(
+            SELECT
+                l.pid,
+                px.gid,
+                l.mode,
+                l.granted,
+                COALESCE(a.usename, px.owner)::TEXT AS usename,
+                a.application_name,
+                a.state,
+                a.query,
+                COALESCE(a.xact_start, px.prepared) AS xact_start,
+                a.query_start,
+                (
+                    EXTRACT(EPOCH FROM clock_timestamp() - COALESCE(a.xact_start, px.prepared))
+                    * 1000
+                )::BIGINT AS xact_age_ms
+            FROM pg_locks l
+            INNER JOIN pg_class c ON c.oid = l.relation
+            INNER JOIN pg_namespace n ON n.oid = c.relnamespace
+            LEFT JOIN pg_stat_activity a ON a.pid = l.pid
+            -- a prepared transaction has no backend: found by the lock on its own xid
+            LEFT JOIN pg_locks x
+                ON l.pid IS NULL
+                AND x.locktype = 'transactionid'
+                AND x.virtualtransaction = l.virtualtransaction
+            LEFT JOIN pg_prepared_xacts px ON px.transaction = x.transactionid
+            WHERE
+                n.nspname = $1::TEXT
+                AND c.relname = $2::TEXT
+                AND l.locktype = 'relation'
+                -- the OIDs are unique within a database only
+                AND l.database = (SELECT oid FROM pg_database WHERE datname = current_database())
+                AND l.pid IS DISTINCT FROM pg_backend_pid()
+            ORDER BY xact_start NULLS LAST, l.pid
+        )"#,
+        schema,
+        table,
+    )
+    .fetch_all(p)
+    .await
+    .map_err(io::Error::other)?;
+
+    Ok(rows
+        .into_iter()
+        .filter_map(|r| {
+            let held: String = r.mode?;
+            let conflicts: bool = LockMode::from_pg(&held).is_some_and(|m| mode.conflicts_with(m));
+            Some(Blocker {
+                pid: r.pid,
+                gid: r.gid,
+                mode: held,
+                granted: r.granted.unwrap_or_default(),
+                conflicts,
+                usename: r.usename,
+                application_name: r.application_name,
+                state: r.state,
+                query: r.query,
+                xact_start: r.xact_start,
+                query_start: r.query_start,
+                xact_age_ms: r.xact_age_ms,
+            })
+        })
+        .collect())
+}
+
+/// What to do if other sessions lock the relation in a conflicting mode.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Enum)]
+pub enum ConflictAction {
+    /// Skips the relation(reported in the result).
+    Skip,
+
+    /// Waits until the blockers release the relation(fails after the deadline).
+    Wait,
+
+    /// Fails with the blocking sessions.
+    Fail,
+}
+
+#[derive(Debug, Clone, Copy, InputObject)]
+pub struct Preflight {
+    pub on_conflict: ConflictAction,
+
+    /// The deadline to wait(10s if not specified).
+    pub wait_ms: Option<i32>,
+}
+
+/// The blockers left after the preflight check(empty if clear).
+pub enum PreflightOutcome {
+    Clear,
+    Skip(Vec<Blocker>),
+}
+
+/// Checks the conflicting locks before running the maintenance of the relation.
+pub async fn preflight(
+    p: &PgPool,
+    schema: &str,
+    table: &str,
+    mode: LockMode,
+    pf: &Preflight,
+) -> Result<PreflightOutcome, MaintenanceError> {
+    let wait: Duration = match pf.wait_ms {
+        None => LOCK_WAIT_DEFAULT,
+        Some(ms) if 0 <= ms => Duration::from_millis(ms as u64),
+        Some(ms) => {
+            return Err(MaintenanceError::InvalidInput(format!(
+                "invalid wait: {ms}"
+            )));
+        }
+    };
+    let deadline = tokio::time::Instant::now() + wait;
+    loop {
+        let conflicting: Vec<Blocker> = blockers(p, schema, table, mode)
+            .await?
+            .into_iter()
+            .filter(|b| b.conflicts)
+            .collect();
+        if conflicting.is_empty() {
+            return Ok(PreflightOutcome::Clear);
+        }
+        let waiting: bool =
+            ConflictAction::Wait == pf.on_conflict && tokio::time::Instant::now() < deadline;
+        match (pf.on_conflict, waiting) {
+            (ConflictAction::Skip, _) => return Ok(PreflightOutcome::Skip(conflicting)),
+            (_, true) => tokio::time::sleep(LOCK_POLL_INTERVAL).await,
+            (_, false) => {
+                return Err(MaintenanceError::LockConflict {
+                    relation: format!("{schema}.{table}"),
+                    blockers: conflicting,
+                });
+            }
+        }
+    }
+}
+
+/// Describes the blockers(e.g, "pid 123(AccessExclusiveLock): ALTER TABLE ...").
+pub fn describe(blockers: &[Blocker]) -> String {
+    let described: Vec<String> = blockers
+        .iter()
+        .map(|b| match (b.pid, &b.gid) {
+            (Some(pid), _) => {
+                let query: &str = b.query.as_deref().unwrap_or("");
+                format!("pid {pid}({}): {query}", b.mode)
+            }
+            (None, gid) => {
+                let gid: &str = gid.as_deref().unwrap_or("unknown");
+                format!("prepared transaction {gid}({})", b.mode)
+            }
+        })
+        .collect();
+    described.join("; ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// In the order of the documentation(AccessShare first).
+    const MODES: [LockMode; 8] = [
+        LockMode::AccessShare,
+        LockMode::RowShare,
+        LockMode::RowExclusive,
+        LockMode::ShareUpdateExclusive,
+        LockMode::Share,
+        LockMode::ShareRowExclusive,
+        LockMode::Exclusive,
+        LockMode::AccessExclusive,
+    ];
+
+    /// The table "Conflicting Lock Modes" of the documentation(X: conflicts).
+    #[rustfmt::skip]
+    const CONFLICTS: [&str; 8] = [
+        ".......X",
+        "......XX",
+        "....XXXX",
+        "...XXXXX",
+        "..XX.XXX",
+        "..XXXXXX",
+        ".XXXXXXX",
+        "XXXXXXXX",
+    ];
+
+    #[test]
+    fn conflicts_with_matches_documentation() {
+        for (i, requested) in MODES.iter().enumerate() {
+            for (j, held) in MODES.iter().enumerate() {
+                let expected: bool = CONFLICTS[i].as_bytes()[j] == b'X';
+                assert_eq!(
+                    requested.conflicts_with(*held),
+                    expected,
+                    "{requested:?} vs {held:?}",
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn conflicts_with_is_symmetric() {
+        for a in MODES {
+            for b in MODES {
+                assert_eq!(a.conflicts_with(b), b.conflicts_with(a), "{a:?} vs {b:?}");
+            }
+        }
+    }
+
+    #[test]
+    fn from_pg_parses_all_modes() {
+        for mode in MODES {
+            assert_eq!(LockMode::from_pg(&format!("{mode:?}Lock")), Some(mode));
+        }
+        assert_eq!(LockMode::from_pg("SIReadLock"), None);
+    }
+
+    #[test]
+    fn taken_by_operation() {
+        let taken = |op, exclusive| LockMode::taken_by(op, exclusive);
+        assert_eq!(
+            taken(MaintenanceOperation::Analyze, false),
+            LockMode::ShareUpdateExclusive
+        );
+        assert_eq!(
+            taken(MaintenanceOperation::Vacuum, true),
+            LockMode::AccessExclusive
+        );
+        assert_eq!(taken(MaintenanceOperation::Reindex, true), LockMode::Share);
+        assert_eq!(
+            taken(MaintenanceOperation::RefreshMaterializedView, false),
+            LockMode::Exclusive
+        );
+    }
+}
@@ -0,0 +1,159 @@
+use std::io;
+
+use sqlx::PgPool;
+
+use async_graphql::SimpleObject;
+
+use crate::error::MaintenanceError;
+use crate::quote_ident;
+use crate::relation::RelKind;
+use crate::relation::RelationChecker;
+
+pub struct UncheckedMatViewName(pub String);
+
+/// A materialized view name which was validated within its schema.
+pub struct CheckedMatViewName {
+    schema: String,
+    name: String,
+}
+
+impl CheckedMatViewName {
+    pub fn as_str(&self) -> &str {
+        &self.name
+    }
+
+    pub fn schema(&self) -> &str {
+        &self.schema
+    }
+
+    /// The quoted, schema-qualified name(e.g, "public"."my_view").
+    pub fn qualified(&self) -> String {
+        format!("{}.{}", quote_ident(&self.schema), quote_ident(&self.name))
+    }
+}
+
+#[async_trait::async_trait]
+pub trait MatViewNameChecker: Sync + Send + 'static {
+    async fn check_matview_name(
+        &self,
+        schema: &str,
+        unchecked: UncheckedMatViewName,
+    ) -> Result<CheckedMatViewName, io::Error>;
+}
+
+#[async_trait::async_trait]
+impl<C> MatViewNameChecker for C
+where
+    C: RelationChecker,
+{
+    async fn check_matview_name(
+        &self,
+        schema: &str,
+        unchecked: UncheckedMatViewName,
+    ) -> Result<CheckedMatViewName, io::Error> {
+        let raw_name: &str = &unchecked.0;
+        match self.relation_kind(schema, raw_name).await? {
+            Some(RelKind::MaterializedView) => Ok(CheckedMatViewName {
+                schema: schema.into(),
+                name: unchecked.0,
+            }),
+            Some(kind) => Err(MaintenanceError::InvalidInput(format!(
+                "the relation {raw_name} is not a materialized view: {kind:?}"
+            ))
+            .into()),
+            None => Err(MaintenanceError::NotFound(format!(
+                "the materialized view {raw_name} not found"
+            ))
+            .into()),
+        }
+    }
+}
+
+#[derive(SimpleObject)]
+pub struct MatViewRefresh {
+    pub schema: String,
+    pub name: String,
+
+    /// true if refreshed concurrently.
+    pub concurrently: bool,
+
+    /// true if a unique index which permits the concurrent refresh exists.
+    pub has_unique_index: bool,
+
+    /// The statement which was(or would be on a dry run) executed.
+    pub sql: String,
+
+    /// false on a dry run(or if skipped for the conflicting locks).
+    pub refreshed: bool,
+
+    /// The estimated total size of the view in bytes(including indexes and toast).
+    pub estimated_bytes: Option<i64>,
+}
+
+pub struct PgRefresh {
+    pub pool: PgPool,
+}
+
+impl PgRefresh {
+    /// Checks if the view has a usable unique index on plain columns without a WHERE clause.
+    ///
+    /// An index left invalid by a failed CREATE INDEX CONCURRENTLY is not usable.
+    pub async fn has_unique_index(&self, view: &CheckedMatViewName) -> Result<bool, io::Error> {
+        let p: &PgPool = &self.pool;
+
+        let ob: Option<bool> = sqlx::query_scalar!(
+            r#"(
+                SELECT EXISTS(
+                    SELECT 1
+                    FROM pg_index i
+                    INNER JOIN pg_class c ON c.oid = i.indrelid
+                    INNER JOIN pg_namespace n ON n.oid = c.relnamespace
+                    WHERE
+                        n.nspname = $1::TEXT
+                        AND c.relname = $2::TEXT
+                        AND i.indisunique
+                        AND i.indisvalid
+                        AND i.indimmediate
+                        AND i.indpred IS NULL
+                        AND i.indexprs IS NULL
+                ) AS found
+            )"#,
+            view.schema(),
+            view.as_str(),
+        )
+        .fetch_one(p)
+        .await
+        .map_err(io::Error::other)?;
+
+        Ok(ob.unwrap_or_default())
+    }
+
+    /// Creates the statement after checking the unique index for CONCURRENTLY.
+    pub async fn refresh_sql(
+        &self,
+        view: &CheckedMatViewName,
+        concurrently: bool,
+    ) -> Result<MatViewRefresh, io::Error> {
+        let has_unique_index: bool = self.has_unique_index(view).await?;
+        if concurrently && !has_unique_index {
+            return Err(MaintenanceError::InvalidInput(format!(
+                "the materialized view {} has no unique index for CONCURRENTLY",
+                view.as_str(),
+            ))
+            .into());
+        }
+        let opt_sql: &str = match concurrently {
+            true => "CONCURRENTLY",
+            false => "",
+        };
+        Ok(MatViewRefresh {
+            schema: view.schema().into(),
+            name: view.as_str().into(),
+            concurrently,
+            has_unique_index,
+            sql: crate::join_sql(&["REFRESH MATERIALIZED VIEW", opt_sql, &view.qualified()]),
+            refreshed: false,
+            estimated_bytes: None,
+        })
+    }
+}
@@ -0,0 +1,230 @@
+use std::sync::Arc;
+
+use crate::CheckedTableName;
+use crate::TableNameChecker;
+use crate::UncheckedTableName;
+use crate::error::MaintenanceError;
+
+/// The schemas denied by the default policy.
+pub const SYSTEM_SCHEMAS: &[&str] = &["pg_catalog", "information_schema", "pg_toast"];
+
+/// Creates the error of a relation rejected by the policy(not "not found").
+pub fn forbidden(msg: String) -> MaintenanceError {
+    MaintenanceError::Forbidden(format!("forbidden: {msg}"))
+}
+
+/// A pattern of the qualified(unquoted) name(e.g, "public.tmp_*").
+#[derive(Clone)]
+pub enum TablePattern {
+    Glob(glob::Pattern),
+    Regex(regex::Regex),
+}
+
+impl TablePattern {
+    pub fn glob(pattern: &str) -> Result<Self, MaintenanceError> {
+        glob::Pattern::new(pattern)
+            .map(Self::Glob)
+            .map_err(|e| MaintenanceError::InvalidInput(format!("invalid glob {pattern}: {e}")))
+    }
+
+    /// Creates an anchored regex(e.g, "audit\\..*_log" matches "audit.login_log" only).
+    pub fn regex(pattern: &str) -> Result<Self, MaintenanceError> {
+        regex::Regex::new(&format!("^(?:{pattern})$"))
+            .map(Self::Regex)
+            .map_err(|e| MaintenanceError::InvalidInput(format!("invalid regex {pattern}: {e}")))
+    }
+
+    pub fn matches(&self, schema: &str, name: &str) -> bool {
+        let qualified: String = format!("{schema}.{name}");
+        match self {
+            Self::Glob(g) => g.matches(&qualified),
+            Self::Regex(r) => r.is_match(&qualified),
+        }
+    }
+}
+
+/// Restricts the relations which can be maintained(denials take precedence).
+#[derive(Clone)]
+pub struct TablePolicy {
+    /// The schemas allowed(all if empty).
+    pub allowed_schemas: Vec<String>,
+    pub denied_schemas: Vec<String>,
+
+    /// The relations allowed(all if empty).
+    pub allowed_tables: Vec<TablePattern>,
+    pub denied_tables: Vec<TablePattern>,
+}
+
+impl Default for TablePolicy {
+    /// Denies the system schemas only.
+    fn default() -> Self {
+        Self {
+            allowed_schemas: vec![],
+            denied_schemas: SYSTEM_SCHEMAS.iter().map(|s| s.to_string()).collect(),
+            allowed_tables: vec![],
+            denied_tables: vec![],
+        }
+    }
+}
+
+impl TablePolicy {
+    pub fn check_schema(&self, schema: &str) -> Result<(), MaintenanceError> {
+        let denied: bool = self.denied_schemas.iter().any(|s| s == schema);
+        let allowed: bool =
+            self.allowed_schemas.is_empty() || self.allowed_schemas.iter().any(|s| s == schema);
+        match !denied && allowed {
+            true => Ok(()),
+            false => Err(forbidden(format!("the schema {schema}"))),
+        }
+    }
+
+    /// true if the relations are restricted by the patterns(not only by the schemas).
+    pub fn has_table_patterns(&self) -> bool {
+        !self.allowed_tables.is_empty() || !self.denied_tables.is_empty()
+    }
+
+    /// Checks the statement on the whole schema(e.g, REINDEX SCHEMA).
+    ///
+    /// Rejected if any table pattern is configured: it would bypass the patterns.
+    pub fn check_schema_wide(&self, schema: &str) -> Result<(), MaintenanceError> {
+        self.check_schema(schema)?;
+        match self.has_table_patterns() {
+            false => Ok(()),
+            true => Err(forbidden(format!(
+                "the whole schema {schema}(restricted by the table patterns)"
+            ))),
+        }
+    }
+
+    /// Keeps the names allowed(e.g, the names discovered by a pattern, not typed in).
+    pub fn allowed_names(&self, schema: &str, names: Vec<String>) -> Vec<String> {
+        names
+            .into_iter()
+            .filter(|name| self.check(schema, name).is_ok())
+            .collect()
+    }
+
+    pub fn check(&self, schema: &str, name: &str) -> Result<(), MaintenanceError> {
+        self.check_schema(schema)?;
+        let denied: bool = self.denied_tables.iter().any(|p| p.matches(schema, name));
+        let allowed: bool = self.allowed_tables.is_empty()
+            || self.allowed_tables.iter().any(|p| p.matches(schema, name));
+        match !denied && allowed {
+            true => Ok(()),
+            false => Err(forbidden(format!("the relation {schema}.{name}"))),
+        }
+    }
+}
+
+/// Applies the policy before the inner checker(e.g, PgTabChk) looks up the names.
+pub struct PolicyChecker<C> {
+    pub inner: C,
+    pub policy: Arc<TablePolicy>,
+}
+
+#[async_trait::async_trait]
+impl<C> TableNameChecker for PolicyChecker<C>
+where
+    C: TableNameChecker,
+{
+    async fn check_table_name(
+        &self,
+        schema: &str,
+        unchecked: UncheckedTableName,
+    ) -> Result<CheckedTableName, MaintenanceError> {
+        self.policy.check(schema, &unchecked.0)?;
+        self.inner.check_table_name(schema, unchecked).await
+    }
+
+    /// Rejects the whole batch if any name is forbidden(before looking up the names).
+    async fn check_table_names(
+        &self,
+        schema: &str,
+        unchecked: Vec<UncheckedTableName>,
+    ) -> Result<Vec<CheckedTableName>, MaintenanceError> {
+        self.policy.check_schema(schema)?;
+        let forbidden_names: Vec<&str> = unchecked
+            .iter()
+            .map(|u| u.0.as_str())
+            .filter(|name| self.policy.check(schema, name).is_err())
+            .collect();
+        if !forbidden_names.is_empty() {
+            return Err(forbidden(format!(
+                "{} relation(s) in {schema}: {}",
+                forbidden_names.len(),
+                forbidden_names.join(", "),
+            )));
+        }
+        self.inner.check_table_names(schema, unchecked).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn glob(pattern: &str) -> TablePattern {
+        TablePattern::glob(pattern).expect("valid glob")
+    }
+
+    fn regex(pattern: &str) -> TablePattern {
+        TablePattern::regex(pattern).expect("valid regex")
+    }
+
+    #[test]
+    fn glob_matches_whole_name() {
+        let p: TablePattern = glob("public.tmp_*");
+        assert!(p.matches("public", "tmp_a"));
+        assert!(p.matches("public", "tmp_"));
+        assert!(!p.matches("public", "tmp"));
+        assert!(!p.matches("public", "my_tmp_a"));
+        assert!(!p.matches("xpublic", "tmp_a"));
+    }
+
+    #[test]
+    fn regex_is_anchored() {
+        let p: TablePattern = regex(r"audit\..*_log");
+        assert!(p.matches("audit", "login_log"));
+        assert!(!p.matches("audit", "login_log_old"));
+        assert!(!p.matches("xaudit", "login_log"));
+    }
+
+    #[test]
+    fn regex_alternation_is_anchored() {
+        let p: TablePattern = regex(r"public\.a|public\.b");
+        assert!(p.matches("public", "a"));
+        assert!(p.matches("public", "b"));
+        assert!(!p.matches("public", "ab"));
+        assert!(!p.matches("xpublic", "b"));
+    }
+
+    #[test]
+    fn invalid_patterns_rejected() {
+        assert_eq!(
+            TablePattern::glob("public.[").err().map(|e| e.code()),
+            Some("INVALID_INPUT"),
+        );
+        assert_eq!(
+            TablePattern::regex("public.(").err().map(|e| e.code()),
+            Some("INVALID_INPUT"),
+        );
+    }
+
+    #[test]
+    fn denials_take_precedence() {
+        let policy = TablePolicy {
+            allowed_tables: vec![glob("public.*")],
+            denied_tables: vec![glob("public.secret_*")],
+            ..Default::default()
+        };
+        assert!(policy.check("public", "orders").is_ok());
+        assert!(policy.check("public", "secret_keys").is_err());
+        assert!(policy.check("other", "orders").is_err());
+        assert!(policy.check("pg_catalog", "pg_class").is_err());
+        assert!(policy.check_schema_wide("public").is_err());
+        assert_eq!(
+            policy.allowed_names("public", vec!["orders".into(), "secret_keys".into()]),
+            vec!["orders".to_string()],
+        );
+    }
+}
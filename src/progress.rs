@@ -0,0 +1,223 @@
+use std::collections::HashMap;
+use std::io;
+use std::sync::Arc;
+use std::time::Duration;
+
+use sqlx::PgPool;
+
+use async_graphql::SimpleObject;
+use async_graphql::futures_util::Stream;
+use async_graphql::futures_util::stream;
+
+use crate::ServerVersion;
+use crate::job;
+use crate::job::Job;
+use crate::job::JobManager;
+
+pub const PROGRESS_INTERVAL_DEFAULT: Duration = Duration::from_millis(1000);
+
+pub const PG_VERSION_PROGRESS_ANALYZE: i32 = 130000;
+pub const PG_VERSION_PROGRESS_CLUSTER: i32 = 120000;
+
+/// A progress snapshot from pg_stat_progress_analyze/vacuum/cluster.
+#[derive(SimpleObject)]
+pub struct MaintenanceProgress {
+    /// The job which is running the statement(if any).
+    pub job_id: Option<i64>,
+
+    pub pid: Option<i32>,
+
+    /// ANALYZE, VACUUM or VACUUM FULL.
+    pub command: Option<String>,
+    pub relation: Option<String>,
+    pub phase: Option<String>,
+
+    pub sample_blks_total: Option<i64>,
+    pub sample_blks_scanned: Option<i64>,
+    pub child_tables_total: Option<i64>,
+    pub child_tables_done: Option<i64>,
+    pub current_child_table: Option<String>,
+
+    pub heap_blks_total: Option<i64>,
+    pub heap_blks_scanned: Option<i64>,
+    pub heap_blks_vacuumed: Option<i64>,
+}
+
+/// Gets the progress of the running ANALYZE(requires PostgreSQL 13 or later).
+async fn progress_analyze(
+    p: &PgPool,
+    pid: Option<i32>,
+) -> Result<Vec<MaintenanceProgress>, io::Error> {
+    sqlx::query_as!(
+        MaintenanceProgress,
+        r#"(
+            SELECT
+                NULL::BIGINT AS job_id,
+                a.pid,
+                'ANALYZE'::TEXT AS command,
+                a.relid::REGCLASS::TEXT AS relation,
+                a.phase,
+                a.sample_blks_total,
+                a.sample_blks_scanned,
+                a.child_tables_total,
+                a.child_tables_done,
+                NULLIF(a.current_child_table_relid, 0)::REGCLASS::TEXT AS current_child_table,
+                NULL::BIGINT AS heap_blks_total,
+                NULL::BIGINT AS heap_blks_scanned,
+                NULL::BIGINT AS heap_blks_vacuumed
+            FROM pg_stat_progress_analyze a
+            WHERE
+                a.datname = current_database()
+                AND ($1::INTEGER IS NULL OR a.pid = $1::INTEGER)
+        )"#,
+        pid,
+    )
+    .fetch_all(p)
+    .await
+    .map_err(io::Error::other)
+}
+
+async fn progress_vacuum(
+    p: &PgPool,
+    pid: Option<i32>,
+) -> Result<Vec<MaintenanceProgress>, io::Error> {
+    sqlx::query_as!(
+        MaintenanceProgress,
+        r#"(
+            SELECT
+                NULL::BIGINT AS job_id,
+                v.pid,
+                'VACUUM'::TEXT AS command,
+                v.relid::REGCLASS::TEXT AS relation,
+                v.phase,
+                NULL::BIGINT AS sample_blks_total,
+                NULL::BIGINT AS sample_blks_scanned,
+                NULL::BIGINT AS child_tables_total,
+                NULL::BIGINT AS child_tables_done,
+                NULL::TEXT AS current_child_table,
+                v.heap_blks_total,
+                v.heap_blks_scanned,
+                v.heap_blks_vacuumed
+            FROM pg_stat_progress_vacuum v
+            WHERE
+                v.datname = current_database()
+                AND ($1::INTEGER IS NULL OR v.pid = $1::INTEGER)
+        )"#,
+        pid,
+    )
+    .fetch_all(p)
+    .await
+    .map_err(io::Error::other)
+}
+
+/// Gets the progress of the running VACUUM FULL(requires PostgreSQL 12 or later).
+async fn progress_vacuum_full(
+    p: &PgPool,
+    pid: Option<i32>,
+) -> Result<Vec<MaintenanceProgress>, io::Error> {
+    sqlx::query_as!(
+        MaintenanceProgress,
+        r#"(
+            SELECT
+                NULL::BIGINT AS job_id,
+                c.pid,
+                c.command,
+                c.relid::REGCLASS::TEXT AS relation,
+                c.phase,
+                NULL::BIGINT AS sample_blks_total,
+                NULL::BIGINT AS sample_blks_scanned,
+                NULL::BIGINT AS child_tables_total,
+                NULL::BIGINT AS child_tables_done,
+                NULL::TEXT AS current_child_table,
+                c.heap_blks_total,
+                c.heap_blks_scanned,
+                NULL::BIGINT AS heap_blks_vacuumed
+            FROM pg_stat_progress_cluster c
+            WHERE
+                c.datname = current_database()
+                AND c.command = 'VACUUM FULL'
+                AND ($1::INTEGER IS NULL OR c.pid = $1::INTEGER)
+        )"#,
+        pid,
+    )
+    .fetch_all(p)
+    .await
+    .map_err(io::Error::other)
+}
+
+/// Gets the progress of the running maintenance(all backends if pid is not specified).
+///
+/// The views missing in the server version are skipped(e.g, no ANALYZE before 13).
+pub async fn progress(
+    p: &PgPool,
+    version: &ServerVersion,
+    pid: Option<i32>,
+) -> Result<Vec<MaintenanceProgress>, io::Error> {
+    let ver: i32 = version.get(p).await?;
+    let mut rows: Vec<MaintenanceProgress> = vec![];
+    if PG_VERSION_PROGRESS_ANALYZE <= ver {
+        rows.extend(progress_analyze(p, pid).await?);
+    }
+    rows.extend(progress_vacuum(p, pid).await?);
+    if PG_VERSION_PROGRESS_CLUSTER <= ver {
+        rows.extend(progress_vacuum_full(p, pid).await?);
+    }
+    Ok(rows)
+}
+
+/// Gets the progress of the job(or the backend) with the job ids filled.
+pub async fn snapshot(
+    p: &PgPool,
+    version: &ServerVersion,
+    jobs: &JobManager,
+    job_id: Option<i64>,
+    pid: Option<i32>,
+) -> Result<Vec<MaintenanceProgress>, io::Error> {
+    let pid: Option<i32> = match job_id {
+        None => pid,
+        Some(id) => {
+            let job: Job = jobs.job(id).ok_or_else(|| job::job_not_found(id))?;
+            match job.backend_pid {
+                // the job is queued or between statements
+                None => return Ok(vec![]),
+                Some(job_pid) => Some(job_pid),
+            }
+        }
+    };
+
+    let pid2job: HashMap<i32, i64> = jobs.running_pids();
+    let mut rows: Vec<MaintenanceProgress> = progress(p, version, pid).await?;
+    for row in &mut rows {
+        row.job_id = row.pid.and_then(|pid| pid2job.get(&pid).copied());
+    }
+    Ok(rows)
+}
+
+/// Polls the progress until the job finishes(forever if no job specified).
+pub fn progress_stream(
+    p: PgPool,
+    version: Arc<ServerVersion>,
+    jobs: Arc<JobManager>,
+    job_id: Option<i64>,
+    pid: Option<i32>,
+    interval: Duration,
+) -> impl Stream<Item = Result<Vec<MaintenanceProgress>, io::Error>> {
+    stream::unfold(Some(true), move |state| {
+        let (p, version, jobs) = (p.clone(), version.clone(), jobs.clone());
+        async move {
+            let first: bool = state?;
+            if !first {
+                tokio::time::sleep(interval).await;
+            }
+            let finished: bool = job_id
+                .and_then(|id| jobs.job(id))
+                .is_some_and(|j| j.is_finished());
+            let res = snapshot(&p, &version, &jobs, job_id, pid).await;
+            let next: Option<bool> = match finished || res.is_err() {
+                true => None,
+                false => Some(false),
+            };
+            Some((res, next))
+        }
+    })
+}
@@ -0,0 +1,183 @@
+use std::io;
+
+use sqlx::PgPool;
+
+use crate::CheckedTableName;
+use crate::ServerVersion;
+use crate::error::MaintenanceError;
+use crate::quote_ident;
+use crate::relation::RelKind;
+
+pub const PG_VERSION_REINDEX_CONCURRENTLY: i32 = 120000;
+
+pub struct UncheckedIndexName(pub String);
+
+/// An index name which was validated within its schema.
+pub struct CheckedIndexName {
+    schema: String,
+    name: String,
+    table: String,
+}
+
+impl CheckedIndexName {
+    pub fn as_str(&self) -> &str {
+        &self.name
+    }
+
+    /// The table of the index(in the same schema).
+    pub fn table(&self) -> &str {
+        &self.table
+    }
+
+    pub fn schema(&self) -> &str {
+        &self.schema
+    }
+
+    /// The quoted, schema-qualified name(e.g, "public"."my_index").
+    pub fn qualified(&self) -> String {
+        format!("{}.{}", quote_ident(&self.schema), quote_ident(&self.name))
+    }
+}
+
+#[async_trait::async_trait]
+pub trait IndexNameChecker: Sync + Send + 'static {
+    async fn check_index_name(
+        &self,
+        schema: &str,
+        unchecked: UncheckedIndexName,
+    ) -> Result<CheckedIndexName, io::Error>;
+}
+
+#[async_trait::async_trait]
+pub trait IndexChecker: Sync + Send + 'static {
+    /// Gets the table of the index(None if the index does not exist).
+    async fn index_table(&self, schema: &str, name: &str) -> Result<Option<String>, io::Error>;
+
+    async fn index_exists(&self, schema: &str, name: &str) -> Result<bool, io::Error> {
+        Ok(self.index_table(schema, name).await?.is_some())
+    }
+}
+
+#[async_trait::async_trait]
+impl<C> IndexNameChecker for C
+where
+    C: IndexChecker,
+{
+    async fn check_index_name(
+        &self,
+        schema: &str,
+        unchecked: UncheckedIndexName,
+    ) -> Result<CheckedIndexName, io::Error> {
+        let raw_name: &str = &unchecked.0;
+        let Some(table) = self.index_table(schema, raw_name).await? else {
+            return Err(
+                MaintenanceError::NotFound(format!("the index {raw_name} not found")).into(),
+            );
+        };
+        Ok(CheckedIndexName {
+            schema: schema.into(),
+            name: unchecked.0,
+            table,
+        })
+    }
+}
+
+pub struct PgIdxChk {
+    pub pool: PgPool,
+}
+
+#[async_trait::async_trait]
+impl IndexChecker for PgIdxChk {
+    async fn index_table(&self, schema: &str, name: &str) -> Result<Option<String>, io::Error> {
+        let p: &PgPool = &self.pool;
+
+        let table: Option<String> = sqlx::query_scalar!(
+            r#"(
+                SELECT tablename::TEXT AS table_name
+                FROM pg_indexes
+                WHERE
+                    schemaname = $1::TEXT
+                    AND indexname = $2::TEXT
+            )"#,
+            schema,
+            name,
+        )
+        .fetch_optional(p)
+        .await
+        .map(|o| o.flatten())
+        .map_err(io::Error::other)?;
+
+        Ok(table)
+    }
+}
+
+pub struct PgReindex {
+    pub pool: PgPool,
+    version: ServerVersion,
+}
+
+impl PgReindex {
+    pub fn new(p: &PgPool) -> Self {
+        Self {
+            pool: p.clone(),
+            version: ServerVersion::default(),
+        }
+    }
+
+    async fn reindex_sql(
+        &self,
+        kind: &str,
+        target: &str,
+        concurrently: bool,
+    ) -> Result<String, io::Error> {
+        let opt_sql: &str = match concurrently {
+            true => {
+                let ver: i32 = self.version.get(&self.pool).await?;
+                if ver < PG_VERSION_REINDEX_CONCURRENTLY {
+                    return Err(MaintenanceError::Unsupported(format!(
+                        "REINDEX CONCURRENTLY not supported: server version {ver}"
+                    ))
+                    .into());
+                }
+                "CONCURRENTLY"
+            }
+            false => "",
+        };
+        Ok(crate::join_sql(&["REINDEX", kind, opt_sql, target]))
+    }
+
+    pub async fn reindex_index_sql(
+        &self,
+        index: &CheckedIndexName,
+        concurrently: bool,
+    ) -> Result<String, io::Error> {
+        self.reindex_sql("INDEX", &index.qualified(), concurrently)
+            .await
+    }
+
+    pub async fn reindex_table_sql(
+        &self,
+        table: &CheckedTableName,
+        concurrently: bool,
+    ) -> Result<String, io::Error> {
+        if RelKind::ForeignTable == table.kind() {
+            return Err(MaintenanceError::InvalidInput(format!(
+                "the table {} is a foreign table which has no indexes",
+                table.as_str(),
+            ))
+            .into());
+        }
+        self.reindex_sql("TABLE", &table.qualified(), concurrently)
+            .await
+    }
+
+    /// Creates the statement to rebuild all indexes in the schema(the name will be quoted).
+    pub async fn reindex_schema_sql(
+        &self,
+        schema: &str,
+        concurrently: bool,
+    ) -> Result<String, io::Error> {
+        self.reindex_sql("SCHEMA", &quote_ident(schema), concurrently)
+            .await
+    }
+}
@@ -0,0 +1,212 @@
+use std::collections::HashMap;
+use std::io;
+
+use sqlx::PgPool;
+
+use async_graphql::Enum;
+
+/// The kind of the relation(pg_class.relkind).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Enum)]
+pub enum RelKind {
+    Table,
+    Index,
+    Sequence,
+    ToastTable,
+    View,
+    MaterializedView,
+    CompositeType,
+    ForeignTable,
+    PartitionedTable,
+    PartitionedIndex,
+}
+
+impl RelKind {
+    pub fn from_relkind(relkind: &str) -> Result<Self, io::Error> {
+        match relkind {
+            "r" => Ok(Self::Table),
+            "i" => Ok(Self::Index),
+            "S" => Ok(Self::Sequence),
+            "t" => Ok(Self::ToastTable),
+            "v" => Ok(Self::View),
+            "m" => Ok(Self::MaterializedView),
+            "c" => Ok(Self::CompositeType),
+            "f" => Ok(Self::ForeignTable),
+            "p" => Ok(Self::PartitionedTable),
+            "I" => Ok(Self::PartitionedIndex),
+            _ => Err(io::Error::other(format!("unknown relkind: {relkind}"))),
+        }
+    }
+
+    /// true if the relation can be analyzed(foreign tables via their FDW).
+    pub fn is_analyzable(&self) -> bool {
+        matches!(
+            self,
+            Self::Table | Self::PartitionedTable | Self::MaterializedView | Self::ForeignTable
+        )
+    }
+
+    /// true if the relation exists as a table for tableExists.
+    ///
+    /// The relations of information_schema.tables(tables, views and foreign tables)
+    /// and materialized views; not indexes, sequences or composite types.
+    pub fn is_table_like(&self) -> bool {
+        self.is_analyzable() || Self::View == *self
+    }
+}
+
+#[async_trait::async_trait]
+pub trait RelationChecker: Sync + Send + 'static {
+    /// Gets the kind of the relation(None if not found).
+    async fn relation_kind(&self, schema: &str, name: &str) -> Result<Option<RelKind>, io::Error>;
+}
+
+/// Gets the kind of the relation from pg_class(None if not found).
+pub async fn relation_kind(
+    p: &PgPool,
+    schema: &str,
+    name: &str,
+) -> Result<Option<RelKind>, io::Error> {
+    let os: Option<String> = sqlx::query_scalar!(
+        r#"(
+            SELECT c.relkind::TEXT AS relkind
+            FROM pg_class c
+            INNER JOIN pg_namespace n ON n.oid = c.relnamespace
+            WHERE
+                n.nspname = $1::TEXT
+                AND c.relname = $2::TEXT
+        )"#,
+        schema,
+        name,
+    )
+    .fetch_optional(p)
+    .await
+    .map(|o| o.flatten())
+    .map_err(io::Error::other)?;
+
+    os.as_deref().map(RelKind::from_relkind).transpose()
+}
+
+/// Gets the kinds of the relations in a single query(unknown names are absent).
+pub async fn relation_kinds(
+    p: &PgPool,
+    schema: &str,
+    names: &[String],
+) -> Result<HashMap<String, RelKind>, io::Error> {
+    let rows = sqlx::query!(
+        r#"(
+            SELECT
+                c.relname::TEXT AS name,
+                c.relkind::TEXT AS relkind
+            FROM pg_class c
+            INNER JOIN pg_namespace n ON n.oid = c.relnamespace
+            WHERE
+                n.nspname = $1::TEXT
+                AND c.relname = ANY($2::TEXT[])
+        )"#,
+        schema,
+        names,
+    )
+    .fetch_all(p)
+    .await
+    .map_err(io::Error::other)?;
+
+    rows.into_iter()
+        .map(|r| {
+            let name: String = r.name.ok_or(io::Error::other("relation name expected"))?;
+            let relkind: String = r.relkind.ok_or(io::Error::other("relkind expected"))?;
+            Ok((name, RelKind::from_relkind(&relkind)?))
+        })
+        .collect()
+}
+
+/// Estimates the total sizes(including indexes and toast) in bytes(unknown names are absent).
+///
+/// Uses pg_class.relpages(updated by VACUUM and ANALYZE) which requires no lock on the
+/// relations, unlike pg_total_relation_size which waits behind an ACCESS EXCLUSIVE lock.
+pub async fn relation_sizes(
+    p: &PgPool,
+    schema: &str,
+    names: &[String],
+) -> Result<HashMap<String, i64>, io::Error> {
+    let rows = sqlx::query!(
+        r#"(
+            SELECT
+                c.relname::TEXT AS name,
+                (
+                    (
+                        c.relpages::BIGINT
+                        + COALESCE(t.relpages, 0)::BIGINT
+                        + COALESCE((
+                            SELECT SUM(i.relpages)
+                            FROM pg_index x
+                            INNER JOIN pg_class i ON i.oid = x.indexrelid
+                            WHERE x.indrelid = c.oid
+                        ), 0)::BIGINT
+                    ) * current_setting('block_size')::BIGINT
+                )::BIGINT AS size
+            FROM pg_class c
+            INNER JOIN pg_namespace n ON n.oid = c.relnamespace
+            LEFT JOIN pg_class t ON t.oid = c.reltoastrelid
+            WHERE
+                n.nspname = $1::TEXT
+                AND c.relname = ANY($2::TEXT[])
+        )"#,
+        schema,
+        names,
+    )
+    .fetch_all(p)
+    .await
+    .map_err(io::Error::other)?;
+
+    rows.into_iter()
+        .map(|r| {
+            let name: String = r.name.ok_or(io::Error::other("relation name expected"))?;
+            Ok((name, r.size.unwrap_or_default()))
+        })
+        .collect()
+}
+
+/// Estimates the total size of the tables and materialized views in the schema.
+pub async fn schema_size(p: &PgPool, schema: &str) -> Result<i64, io::Error> {
+    let names: Vec<String> = maintainable_names(p, schema, "%").await?;
+    let sizes: HashMap<String, i64> = relation_sizes(p, schema, &names).await?;
+    Ok(sizes.values().sum())
+}
+
+/// Gets the names of the tables and materialized views matching the pattern(LIKE).
+pub async fn maintainable_names(
+    p: &PgPool,
+    schema: &str,
+    pattern: &str,
+) -> Result<Vec<String>, io::Error> {
+    let names: Vec<Option<String>> = sqlx::query_scalar!(
+        r#"(
+            SELECT c.relname::TEXT AS name
+            FROM pg_class c
+            INNER JOIN pg_namespace n ON n.oid = c.relnamespace
+            WHERE
+                n.nspname = $1::TEXT
+                AND c.relname LIKE $2::TEXT
+                AND c.relkind IN ('r', 'p', 'm')
+            ORDER BY c.relname
+        )"#,
+        schema,
+        pattern,
+    )
+    .fetch_all(p)
+    .await
+    .map_err(io::Error::other)?;
+
+    Ok(names.into_iter().flatten().collect())
+}
+
+pub struct PgRelChk {
+    pub pool: PgPool,
+}
+
+#[async_trait::async_trait]
+impl RelationChecker for PgRelChk {
+    async fn relation_kind(&self, schema: &str, name: &str) -> Result<Option<RelKind>, io::Error> {
+        relation_kind(&self.pool, schema, name).await
+    }
+}
@@ -0,0 +1,193 @@
+use std::collections::HashSet;
+use std::io;
+use std::time::Duration;
+
+use crate::error::MaintenanceError;
+
+/// Not retried unless configured.
+pub const RETRY_MAX_ATTEMPTS_DEFAULT: u32 = 1;
+pub const RETRY_INITIAL_BACKOFF_DEFAULT: Duration = Duration::from_millis(200);
+pub const RETRY_MAX_BACKOFF_DEFAULT: Duration = Duration::from_secs(10);
+
+/// The transient failures(e.g, 40001: serialization_failure, 55P03: lock_not_available).
+pub const RETRYABLE_SQLSTATES_DEFAULT: &[&str] = &[
+    "40001", "40P01", "55P03", "57P01", "08000", "08003", "08006",
+];
+
+/// Retries the statements failed by the transient failures.
+#[derive(Clone)]
+pub struct RetryPolicy {
+    /// The executions of each statement(1: not retried).
+    pub max_attempts: u32,
+
+    /// The delay before the first retry(doubled for each retry).
+    pub initial_backoff: Duration,
+    pub max_backoff: Duration,
+
+    /// The SQLSTATEs retried(the connection errors without SQLSTATE are also retried).
+    pub retryable_sqlstates: HashSet<String>,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_attempts: RETRY_MAX_ATTEMPTS_DEFAULT,
+            initial_backoff: RETRY_INITIAL_BACKOFF_DEFAULT,
+            max_backoff: RETRY_MAX_BACKOFF_DEFAULT,
+            retryable_sqlstates: RETRYABLE_SQLSTATES_DEFAULT
+                .iter()
+                .map(|s| s.to_string())
+                .collect(),
+        }
+    }
+}
+
+impl RetryPolicy {
+    pub fn is_retryable(&self, e: &MaintenanceError) -> bool {
+        match e.sqlstate() {
+            Some(sqlstate) => self.retryable_sqlstates.contains(sqlstate),
+            None => matches!(e, MaintenanceError::Connection(_)),
+        }
+    }
+
+    /// The delay before the retry(1: the first retry) with the jitter of up to the half.
+    pub fn backoff(&self, retry: u32) -> Duration {
+        let factor: u32 = 2u32.saturating_pow(retry.saturating_sub(1));
+        let delay: Duration = self
+            .initial_backoff
+            .saturating_mul(factor)
+            .min(self.max_backoff);
+        let half: Duration = delay / 2;
+        let jitter: u64 = fastrand::u64(0..=half.as_millis() as u64);
+        half + Duration::from_millis(jitter)
+    }
+
+    /// Executes until succeeded, failed permanently or the attempts are exhausted.
+    ///
+    /// Returns the result of the last attempt and the number of the attempts.
+    pub async fn run<F, Fut, T>(&self, mut execute: F) -> (Result<T, io::Error>, i32)
+    where
+        F: FnMut() -> Fut,
+        Fut: Future<Output = Result<T, io::Error>>,
+    {
+        let mut attempts: u32 = 1;
+        loop {
+            let res: Result<T, io::Error> = execute().await;
+            let retry: bool = match &res {
+                Ok(_) => false,
+                Err(e) => {
+                    attempts < self.max_attempts
+                        && self.is_retryable(&MaintenanceError::from_io_ref(e))
+                }
+            };
+            if !retry {
+                return (res, attempts as i32);
+            }
+            tokio::time::sleep(self.backoff(attempts)).await;
+            attempts += 1;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::error::DbError;
+
+    fn policy(max_attempts: u32) -> RetryPolicy {
+        RetryPolicy {
+            max_attempts,
+            initial_backoff: Duration::from_millis(200),
+            max_backoff: Duration::from_secs(1),
+            ..Default::default()
+        }
+    }
+
+    fn db_error(sqlstate: &str) -> io::Error {
+        MaintenanceError::Database(DbError {
+            sqlstate: sqlstate.into(),
+            message: "failed".into(),
+        })
+        .into()
+    }
+
+    #[test]
+    fn backoff_doubles_within_jitter() {
+        let p: RetryPolicy = policy(5);
+        for _ in 0..100 {
+            for (retry, ms) in [(1, 200), (2, 400), (3, 800)] {
+                let delay: Duration = p.backoff(retry);
+                assert!(Duration::from_millis(ms / 2) <= delay, "{retry}: {delay:?}");
+                assert!(delay <= Duration::from_millis(ms), "{retry}: {delay:?}");
+            }
+        }
+    }
+
+    #[test]
+    fn backoff_capped_by_max() {
+        let p: RetryPolicy = policy(5);
+        for retry in [4, 10, 32, 33, u32::MAX] {
+            let delay: Duration = p.backoff(retry);
+            assert!(Duration::from_millis(500) <= delay, "{retry}: {delay:?}");
+            assert!(delay <= Duration::from_secs(1), "{retry}: {delay:?}");
+        }
+    }
+
+    #[test]
+    fn backoff_zero_without_initial() {
+        let p = RetryPolicy {
+            initial_backoff: Duration::ZERO,
+            ..policy(5)
+        };
+        assert_eq!(p.backoff(3), Duration::ZERO);
+    }
+
+    #[test]
+    fn retryable_errors() {
+        let p: RetryPolicy = policy(3);
+        let from = |e: io::Error| MaintenanceError::from_io_ref(&e);
+        assert!(p.is_retryable(&from(db_error("40P01"))));
+        assert!(p.is_retryable(&MaintenanceError::Connection("lost".into())));
+        assert!(!p.is_retryable(&from(db_error("42501"))));
+        assert!(!p.is_retryable(&MaintenanceError::InvalidInput("bad".into())));
+    }
+
+    fn run_with(p: &RetryPolicy, errors: Vec<&str>) -> (Result<(), io::Error>, i32) {
+        let rt = tokio::runtime::Builder::new_current_thread()
+            .enable_time()
+            .build()
+            .expect("runtime");
+        let mut errors = errors.into_iter();
+        rt.block_on(p.run(|| {
+            let next: Option<&str> = errors.next();
+            async move { next.map_or(Ok(()), |sqlstate| Err(db_error(sqlstate))) }
+        }))
+    }
+
+    #[test]
+    fn run_retries_until_succeeded() {
+        let p = RetryPolicy {
+            initial_backoff: Duration::ZERO,
+            ..policy(3)
+        };
+        let (res, attempts) = run_with(&p, vec!["40001", "55P03"]);
+        assert!(res.is_ok());
+        assert_eq!(attempts, 3);
+    }
+
+    #[test]
+    fn run_stops_on_exhausted_or_permanent_failures() {
+        let p = RetryPolicy {
+            initial_backoff: Duration::ZERO,
+            ..policy(2)
+        };
+        let (res, attempts) = run_with(&p, vec!["40001", "40001", "40001"]);
+        assert!(res.is_err());
+        assert_eq!(attempts, 2);
+
+        let (res, attempts) = run_with(&p, vec!["42501"]);
+        assert!(res.is_err());
+        assert_eq!(attempts, 1);
+    }
+}
@@ -0,0 +1,277 @@
+use std::collections::BTreeMap;
+use std::io;
+use std::str::FromStr;
+use std::sync::Arc;
+use std::sync::Mutex;
+use std::sync::MutexGuard;
+use std::sync::PoisonError;
+use std::sync::Weak;
+use std::sync::atomic::AtomicI64;
+use std::sync::atomic::Ordering;
+use std::time::Duration;
+
+use chrono::DateTime;
+use chrono::Utc;
+
+use sqlx::PgPool;
+
+use async_graphql::InputObject;
+use async_graphql::SimpleObject;
+
+use crate::AnalyzeOptions;
+use crate::CheckedTableName;
+use crate::RunSettings;
+use crate::TableNameChecker;
+use crate::UncheckedTableName;
+use crate::error::MaintenanceError;
+use crate::job::Job;
+use crate::job::JobManager;
+use crate::job::JobOperation;
+use crate::job::JobTask;
+use crate::locks::Preflight;
+use crate::policy::TablePolicy;
+use crate::relation;
+use crate::timeout::Timeouts;
+use crate::vacuum::VacuumOptions;
+
+pub const SCHEDULER_TICK: Duration = Duration::from_secs(1);
+
+/// A maintenance schedule defined in the config or created via createSchedule.
+#[derive(Clone, InputObject)]
+pub struct ScheduleInput {
+    pub name: String,
+
+    /// The cron expression in UTC(e.g, "0 3 * * *": 03:00 every day).
+    ///
+    /// The seconds field can be prepended(e.g, "30 0 3 * * *").
+    pub cron: String,
+
+    pub schema: String,
+
+    /// The LIKE pattern of the tables and materialized views(all if not specified).
+    pub pattern: Option<String>,
+
+    pub operation: JobOperation,
+    pub analyze_options: Option<AnalyzeOptions>,
+    pub vacuum_options: Option<VacuumOptions>,
+    pub continue_on_error: Option<bool>,
+    pub timeouts: Option<Timeouts>,
+    pub preflight: Option<Preflight>,
+}
+
+impl ScheduleInput {
+    fn task(&self) -> Result<JobTask, io::Error> {
+        match (self.operation, &self.analyze_options, &self.vacuum_options) {
+            (JobOperation::Analyze, a, None) => Ok(JobTask::Analyze(a.clone().unwrap_or_default())),
+            (JobOperation::Vacuum, None, v) => Ok(JobTask::Vacuum(v.clone().unwrap_or_default())),
+            (op, _, _) => Err(MaintenanceError::InvalidInput(format!(
+                "options of another operation specified for {op:?}"
+            ))
+            .into()),
+        }
+    }
+}
+
+#[derive(Clone, SimpleObject)]
+pub struct Schedule {
+    pub id: i64,
+    pub name: String,
+    pub cron: String,
+    pub schema: String,
+    pub pattern: String,
+    pub operation: JobOperation,
+
+    /// None if the expression has no future run.
+    pub next_run: Option<DateTime<Utc>>,
+    pub last_run: Option<DateTime<Utc>>,
+
+    /// The job submitted by the last run.
+    pub last_job_id: Option<i64>,
+
+    /// The error of the last run(e.g, no matching tables).
+    pub last_error: Option<String>,
+}
+
+struct ScheduleEntry {
+    schedule: Schedule,
+    cron: cron::Schedule,
+    task: JobTask,
+    keep_going: bool,
+    timeouts: Option<Timeouts>,
+    preflight: Option<Preflight>,
+}
+
+/// A run of the schedule to be submitted as a job.
+struct DueRun {
+    id: i64,
+    name: String,
+    schema: String,
+    pattern: String,
+    task: JobTask,
+    keep_going: bool,
+    timeouts: Option<Timeouts>,
+    preflight: Option<Preflight>,
+}
+
+/// Parses the cron expression(5 fields: minute precision, 6 or 7 fields: with seconds).
+pub fn parse_cron(expr: &str) -> Result<cron::Schedule, io::Error> {
+    let normalized: String = match expr.split_whitespace().count() {
+        5 => format!("0 {expr}"),
+        _ => expr.into(),
+    };
+    cron::Schedule::from_str(&normalized).map_err(|e| {
+        MaintenanceError::InvalidInput(format!("invalid cron expression {expr}: {e}")).into()
+    })
+}
+
+/// Submits the jobs of the schedules(in memory) when they are due.
+pub struct Scheduler {
+    schedules: Mutex<BTreeMap<i64, ScheduleEntry>>,
+    next_id: AtomicI64,
+    pool: PgPool,
+    checker: Box<dyn TableNameChecker>,
+
+    /// Filters the matching tables before the checker rejects the forbidden ones.
+    policy: Arc<TablePolicy>,
+    jobs: Arc<JobManager>,
+}
+
+impl Scheduler {
+    /// Creates the scheduler and spawns its ticker(requires a tokio runtime).
+    pub fn start(
+        p: &PgPool,
+        checker: Box<dyn TableNameChecker>,
+        policy: Arc<TablePolicy>,
+        jobs: Arc<JobManager>,
+    ) -> Arc<Self> {
+        let scheduler = Arc::new(Self {
+            schedules: Mutex::new(BTreeMap::new()),
+            next_id: AtomicI64::new(1),
+            pool: p.clone(),
+            checker,
+            policy,
+            jobs,
+        });
+        tokio::spawn(ticker(Arc::downgrade(&scheduler)));
+        scheduler
+    }
+
+    fn lock(&self) -> MutexGuard<'_, BTreeMap<i64, ScheduleEntry>> {
+        self.schedules
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+    }
+
+    pub fn create(&self, input: ScheduleInput) -> Result<Schedule, io::Error> {
+        let cron: cron::Schedule = parse_cron(&input.cron)?;
+        let task: JobTask = input.task()?;
+        let id: i64 = self.next_id.fetch_add(1, Ordering::SeqCst);
+        let schedule = Schedule {
+            id,
+            next_run: cron.after(&Utc::now()).next(),
+            name: input.name,
+            cron: input.cron,
+            schema: input.schema,
+            pattern: input.pattern.unwrap_or_else(|| "%".into()),
+            operation: input.operation,
+            last_run: None,
+            last_job_id: None,
+            last_error: None,
+        };
+        let entry = ScheduleEntry {
+            schedule: schedule.clone(),
+            cron,
+            task,
+            keep_going: input.continue_on_error.unwrap_or_default(),
+            timeouts: input.timeouts,
+            preflight: input.preflight,
+        };
+        self.lock().insert(id, entry);
+        Ok(schedule)
+    }
+
+    pub fn delete(&self, id: i64) -> Option<Schedule> {
+        self.lock().remove(&id).map(|e| e.schedule)
+    }
+
+    pub fn schedule(&self, id: i64) -> Option<Schedule> {
+        self.lock().get(&id).map(|e| e.schedule.clone())
+    }
+
+    pub fn schedules(&self) -> Vec<Schedule> {
+        self.lock().values().map(|e| e.schedule.clone()).collect()
+    }
+
+    /// Gets the due runs and advances their next runs.
+    fn due(&self, now: DateTime<Utc>) -> Vec<DueRun> {
+        let mut schedules = self.lock();
+        schedules
+            .values_mut()
+            .filter(|e| e.schedule.next_run.is_some_and(|next| next <= now))
+            .map(|e| {
+                e.schedule.next_run = e.cron.after(&now).next();
+                DueRun {
+                    id: e.schedule.id,
+                    name: e.schedule.name.clone(),
+                    schema: e.schedule.schema.clone(),
+                    pattern: e.schedule.pattern.clone(),
+                    task: e.task.clone(),
+                    keep_going: e.keep_going,
+                    timeouts: e.timeouts,
+                    preflight: e.preflight,
+                }
+            })
+            .collect()
+    }
+
+    /// Checks the matching tables and submits them as a job.
+    async fn submit(&self, run: DueRun) -> Result<Job, io::Error> {
+        let names: Vec<String> =
+            relation::maintainable_names(&self.pool, &run.schema, &run.pattern).await?;
+        let names: Vec<String> = self.policy.allowed_names(&run.schema, names);
+        if names.is_empty() {
+            return Err(io::Error::other(format!(
+                "no allowed tables match {} in {}",
+                run.pattern, run.schema,
+            )));
+        }
+        let unchecked: Vec<UncheckedTableName> =
+            names.into_iter().map(UncheckedTableName).collect();
+        let checked: Vec<CheckedTableName> = self
+            .checker
+            .check_table_names(&run.schema, unchecked)
+            .await?;
+        let settings = RunSettings {
+            dry_run: false,
+            timeouts: run.timeouts,
+            preflight: run.preflight,
+            actor: Some(format!("schedule {}", run.name)),
+        };
+        self.jobs
+            .submit(run.schema, checked, run.task, run.keep_going, settings)
+    }
+
+    async fn tick(&self) {
+        let now: DateTime<Utc> = Utc::now();
+        for run in self.due(now) {
+            let id: i64 = run.id;
+            let res: Result<Job, io::Error> = self.submit(run).await;
+            if let Some(e) = self.lock().get_mut(&id) {
+                e.schedule.last_run = Some(now);
+                e.schedule.last_job_id = res.as_ref().ok().map(|j| j.id);
+                e.schedule.last_error = res.err().map(|e| e.to_string());
+            }
+        }
+    }
+}
+
+async fn ticker(scheduler: Weak<Scheduler>) {
+    let mut interval = tokio::time::interval(SCHEDULER_TICK);
+    loop {
+        interval.tick().await;
+        let Some(s) = scheduler.upgrade() else {
+            return;
+        };
+        s.tick().await;
+    }
+}
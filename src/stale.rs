@@ -0,0 +1,201 @@
+use std::io;
+
+use chrono::DateTime;
+use chrono::Utc;
+
+use sqlx::PgPool;
+
+use async_graphql::SimpleObject;
+
+/// The autovacuum analyze threshold: base + scale_factor * reltuples.
+#[derive(Clone, Copy)]
+pub struct AnalyzeThreshold {
+    pub base: i64,
+    pub scale_factor: f64,
+}
+
+impl AnalyzeThreshold {
+    /// Overrides the settings using the per-table reloptions(e.g, autovacuum_analyze_threshold=50).
+    pub fn with_reloptions(&self, reloptions: &[String]) -> Self {
+        let mut t: Self = *self;
+        for opt in reloptions {
+            let Some((key, val)) = opt.split_once('=') else {
+                continue;
+            };
+            match key {
+                "autovacuum_analyze_threshold" => {
+                    t.base = val.parse().unwrap_or(t.base);
+                }
+                "autovacuum_analyze_scale_factor" => {
+                    t.scale_factor = val.parse().unwrap_or(t.scale_factor);
+                }
+                _ => {}
+            }
+        }
+        t
+    }
+
+    /// Computes the threshold(reltuples is -1 if the table was never analyzed).
+    pub fn threshold(&self, reltuples: f64) -> f64 {
+        (self.base as f64) + self.scale_factor * reltuples.max(0.0)
+    }
+}
+
+/// Gets the global autovacuum_analyze_threshold/autovacuum_analyze_scale_factor.
+pub async fn global_threshold(p: &PgPool) -> Result<AnalyzeThreshold, io::Error> {
+    let row = sqlx::query!(
+        r#"(
+            SELECT
+                current_setting('autovacuum_analyze_threshold')::BIGINT AS base,
+                current_setting('autovacuum_analyze_scale_factor')::FLOAT8 AS scale_factor
+        )"#,
+    )
+    .fetch_one(p)
+    .await
+    .map_err(io::Error::other)?;
+
+    Ok(AnalyzeThreshold {
+        base: row
+            .base
+            .ok_or(io::Error::other("analyze threshold expected"))?,
+        scale_factor: row
+            .scale_factor
+            .ok_or(io::Error::other("analyze scale factor expected"))?,
+    })
+}
+
+/// A table whose modified rows since the last analyze exceed the threshold.
+#[derive(SimpleObject)]
+pub struct StaleTable {
+    pub schema: String,
+    pub table: String,
+
+    /// The estimated number of rows(-1 if never analyzed).
+    pub reltuples: f64,
+    pub n_mod_since_analyze: i64,
+
+    pub base_threshold: i64,
+    pub scale_factor: f64,
+    pub analyze_threshold: f64,
+
+    pub last_analyze: Option<DateTime<Utc>>,
+    pub last_autoanalyze: Option<DateTime<Utc>>,
+}
+
+/// Gets the stale tables whose names match the LIKE pattern(most stale first).
+pub async fn stale_tables(
+    p: &PgPool,
+    schema: &str,
+    pattern: &str,
+) -> Result<Vec<StaleTable>, io::Error> {
+    let global: AnalyzeThreshold = global_threshold(p).await?;
+
+    let rows = sqlx::query!(
+        r#"(
+            SELECT
+                s.schemaname::TEXT AS schema,
+                s.relname::TEXT AS table,
+                c.reltuples::FLOAT8 AS reltuples,
+                s.n_mod_since_analyze,
+                c.reloptions,
+                s.last_analyze,
+                s.last_autoanalyze
+            FROM pg_stat_user_tables s
+            INNER JOIN pg_class c ON c.oid = s.relid
+            WHERE
+                s.schemaname = $1::TEXT
+                AND s.relname LIKE $2::TEXT
+        )"#,
+        schema,
+        pattern,
+    )
+    .fetch_all(p)
+    .await
+    .map_err(io::Error::other)?;
+
+    let mut stale: Vec<StaleTable> = vec![];
+    for r in rows {
+        let reltuples: f64 = r.reltuples.unwrap_or(-1.0);
+        let n_mod: i64 = r.n_mod_since_analyze.unwrap_or_default();
+        let t: AnalyzeThreshold = global.with_reloptions(&r.reloptions.unwrap_or_default());
+        let analyze_threshold: f64 = t.threshold(reltuples);
+        if (n_mod as f64) <= analyze_threshold {
+            continue;
+        }
+        stale.push(StaleTable {
+            schema: r.schema.ok_or(io::Error::other("schema name expected"))?,
+            table: r.table.ok_or(io::Error::other("table name expected"))?,
+            reltuples,
+            n_mod_since_analyze: n_mod,
+            base_threshold: t.base,
+            scale_factor: t.scale_factor,
+            analyze_threshold,
+            last_analyze: r.last_analyze,
+            last_autoanalyze: r.last_autoanalyze,
+        });
+    }
+
+    stale.sort_by(|a, b| {
+        let ra: f64 = (a.n_mod_since_analyze as f64) / a.analyze_threshold.max(1.0);
+        let rb: f64 = (b.n_mod_since_analyze as f64) / b.analyze_threshold.max(1.0);
+        rb.total_cmp(&ra)
+    });
+    Ok(stale)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const GLOBAL: AnalyzeThreshold = AnalyzeThreshold {
+        base: 50,
+        scale_factor: 0.1,
+    };
+
+    fn reloptions(opts: &[&str]) -> Vec<String> {
+        opts.iter().map(|s| s.to_string()).collect()
+    }
+
+    #[test]
+    fn overrides_each_key() {
+        let base = GLOBAL.with_reloptions(&reloptions(&["autovacuum_analyze_threshold=1000"]));
+        assert_eq!(base.base, 1000);
+        assert_eq!(base.scale_factor, 0.1);
+
+        let scale = GLOBAL.with_reloptions(&reloptions(&["autovacuum_analyze_scale_factor=0.5"]));
+        assert_eq!(scale.base, 50);
+        assert_eq!(scale.scale_factor, 0.5);
+
+        let both = GLOBAL.with_reloptions(&reloptions(&[
+            "fillfactor=70",
+            "autovacuum_analyze_scale_factor=0.25",
+            "autovacuum_analyze_threshold=0",
+        ]));
+        assert_eq!(both.base, 0);
+        assert_eq!(both.scale_factor, 0.25);
+        assert_eq!(both.threshold(1000.0), 250.0);
+    }
+
+    #[test]
+    fn unparsable_values_fall_back() {
+        let t = GLOBAL.with_reloptions(&reloptions(&[
+            "autovacuum_analyze_threshold=many",
+            "autovacuum_analyze_scale_factor=",
+            "autovacuum_analyze_threshold",
+            "autovacuum_vacuum_threshold=1",
+        ]));
+        assert_eq!(t.base, 50);
+        assert_eq!(t.scale_factor, 0.1);
+    }
+
+    #[test]
+    fn never_analyzed() {
+        let t = AnalyzeThreshold {
+            base: 50,
+            scale_factor: 0.5,
+        };
+        assert_eq!(t.threshold(-1.0), 50.0);
+        assert_eq!(t.threshold(0.0), 50.0);
+        assert_eq!(t.threshold(100.0), 100.0);
+    }
+}
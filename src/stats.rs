@@ -0,0 +1,108 @@
+use std::io;
+
+use chrono::DateTime;
+use chrono::Utc;
+
+use sqlx::PgPool;
+
+use async_graphql::SimpleObject;
+
+use crate::ServerVersion;
+
+pub const PG_VERSION_N_INS_SINCE_VACUUM: i32 = 130000;
+
+/// The statistics of a table from pg_stat_user_tables.
+#[derive(SimpleObject)]
+pub struct TableStats {
+    pub schema: Option<String>,
+    pub table: Option<String>,
+
+    pub n_live_tup: Option<i64>,
+    pub n_dead_tup: Option<i64>,
+    pub n_mod_since_analyze: Option<i64>,
+
+    /// Requires PostgreSQL 13 or later(null before).
+    pub n_ins_since_vacuum: Option<i64>,
+
+    pub last_analyze: Option<DateTime<Utc>>,
+    pub last_autoanalyze: Option<DateTime<Utc>>,
+    pub last_vacuum: Option<DateTime<Utc>>,
+    pub last_autovacuum: Option<DateTime<Utc>>,
+
+    pub analyze_count: Option<i64>,
+    pub autoanalyze_count: Option<i64>,
+    pub vacuum_count: Option<i64>,
+    pub autovacuum_count: Option<i64>,
+}
+
+/// Gets the statistics of the tables whose names match the LIKE pattern.
+pub async fn table_stats(
+    p: &PgPool,
+    version: &ServerVersion,
+    schema: &str,
+    pattern: &str,
+) -> Result<Vec<TableStats>, io::Error> {
+    let ver: i32 = version.get(p).await?;
+    match PG_VERSION_N_INS_SINCE_VACUUM <= ver {
+        true => sqlx::query_as!(
+            TableStats,
+            r#"(
+                    SELECT
+                        schemaname::TEXT AS schema,
+                        relname::TEXT AS table,
+                        n_live_tup,
+                        n_dead_tup,
+                        n_mod_since_analyze,
+                        n_ins_since_vacuum,
+                        last_analyze,
+                        last_autoanalyze,
+                        last_vacuum,
+                        last_autovacuum,
+                        analyze_count,
+                        autoanalyze_count,
+                        vacuum_count,
+                        autovacuum_count
+                    FROM pg_stat_user_tables
+                    WHERE
+                        schemaname = $1::TEXT
+                        AND relname LIKE $2::TEXT
+                    ORDER BY relname
+                )"#,
+            schema,
+            pattern,
+        )
+        .fetch_all(p)
+        .await
+        .map_err(io::Error::other),
+        false => sqlx::query_as!(
+            TableStats,
+            r#"(
+                    SELECT
+                        schemaname::TEXT AS schema,
+                        relname::TEXT AS table,
+                        n_live_tup,
+                        n_dead_tup,
+                        n_mod_since_analyze,
+                        NULL::BIGINT AS n_ins_since_vacuum,
+                        last_analyze,
+                        last_autoanalyze,
+                        last_vacuum,
+                        last_autovacuum,
+                        analyze_count,
+                        autoanalyze_count,
+                        vacuum_count,
+                        autovacuum_count
+                    FROM pg_stat_user_tables
+                    WHERE
+                        schemaname = $1::TEXT
+                        AND relname LIKE $2::TEXT
+                    ORDER BY relname
+                )"#,
+            schema,
+            pattern,
+        )
+        .fetch_all(p)
+        .await
+        .map_err(io::Error::other),
+    }
+}
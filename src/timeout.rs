@@ -0,0 +1,174 @@
+use std::collections::HashMap;
+
+use sqlx::Acquire;
+use sqlx::PgConnection;
+use sqlx::PgPool;
+
+use async_graphql::InputObject;
+
+use crate::batch::MaintenanceOperation;
+use crate::error::MaintenanceError;
+
+/// The timeouts of a statement(the server settings are used if not specified).
+#[derive(Debug, Default, Clone, Copy, InputObject)]
+pub struct Timeouts {
+    /// Gives up waiting for the lock(e.g, ShareUpdateExclusiveLock) after the duration.
+    pub lock_timeout_ms: Option<i32>,
+
+    /// Cancels the statement after the duration.
+    pub statement_timeout_ms: Option<i32>,
+}
+
+impl Timeouts {
+    /// Uses the fallback for the unspecified timeouts.
+    pub fn or(self, fallback: Self) -> Self {
+        Self {
+            lock_timeout_ms: self.lock_timeout_ms.or(fallback.lock_timeout_ms),
+            statement_timeout_ms: self.statement_timeout_ms.or(fallback.statement_timeout_ms),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.lock_timeout_ms.is_none() && self.statement_timeout_ms.is_none()
+    }
+
+    /// Gets the settings to be changed(0 disables the timeout).
+    fn settings(&self) -> Result<Vec<(&'static str, String)>, MaintenanceError> {
+        let pairs = [
+            ("lock_timeout", self.lock_timeout_ms),
+            ("statement_timeout", self.statement_timeout_ms),
+        ];
+        pairs
+            .into_iter()
+            .filter_map(|(name, o)| o.map(|ms| (name, ms)))
+            .map(|(name, ms)| match 0 <= ms {
+                true => Ok((name, ms.to_string())),
+                false => Err(MaintenanceError::InvalidInput(format!(
+                    "invalid {name}: {ms}"
+                ))),
+            })
+            .collect()
+    }
+}
+
+/// The timeouts of the operations(e.g, a short lock_timeout for ANALYZE).
+#[derive(Clone, Default)]
+pub struct TimeoutPolicy {
+    /// Used for the operations not configured.
+    pub default: Timeouts,
+    pub operations: HashMap<MaintenanceOperation, Timeouts>,
+}
+
+impl TimeoutPolicy {
+    /// Gets the timeouts requested by the caller(or configured).
+    pub fn timeouts(
+        &self,
+        operation: MaintenanceOperation,
+        requested: Option<Timeouts>,
+    ) -> Timeouts {
+        let configured: Timeouts = self
+            .operations
+            .get(&operation)
+            .copied()
+            .unwrap_or_default()
+            .or(self.default);
+        requested.unwrap_or_default().or(configured)
+    }
+}
+
+async fn set_config(
+    conn: &mut PgConnection,
+    name: &str,
+    value: &str,
+    is_local: bool,
+) -> Result<(), MaintenanceError> {
+    sqlx::query_scalar!(
+        r#"(
+            SELECT set_config($1::TEXT, $2::TEXT, $3::BOOLEAN) AS value
+        )"#,
+        name,
+        value,
+        is_local,
+    )
+    .fetch_one(conn)
+    .await?;
+    Ok(())
+}
+
+/// Executes the statement in a transaction with the timeouts(SET LOCAL).
+pub async fn execute_in_transaction(
+    conn: &mut PgConnection,
+    sql: &str,
+    timeouts: &Timeouts,
+) -> Result<(), MaintenanceError> {
+    let mut tx = conn.begin().await?;
+    for (name, value) in timeouts.settings()? {
+        set_config(&mut tx, name, &value, true).await?;
+    }
+    sqlx::query(sql).execute(&mut *tx).await?;
+    tx.commit().await?;
+    Ok(())
+}
+
+/// Executes the statement which cannot run in a transaction block(e.g, VACUUM).
+///
+/// The timeouts are set for the session and reset afterwards.
+pub async fn execute_in_session(
+    conn: &mut PgConnection,
+    sql: &str,
+    timeouts: &Timeouts,
+) -> Result<(), MaintenanceError> {
+    let settings: Vec<(&str, String)> = timeouts.settings()?;
+    for (name, value) in &settings {
+        set_config(conn, name, value, false).await?;
+    }
+    let res: Result<(), MaintenanceError> = sqlx::query(sql)
+        .execute(&mut *conn)
+        .await
+        .map(|_| ())
+        .map_err(MaintenanceError::from);
+    for (name, _) in &settings {
+        sqlx::query(&format!("RESET {name}"))
+            .execute(&mut *conn)
+            .await?;
+    }
+    res
+}
+
+/// Executes the statement of the operation with the timeouts.
+pub async fn execute_with_timeouts(
+    conn: &mut PgConnection,
+    operation: MaintenanceOperation,
+    sql: &str,
+    timeouts: &Timeouts,
+) -> Result<(), MaintenanceError> {
+    if timeouts.is_empty() {
+        return Ok(crate::execute_sql(conn, sql).await?);
+    }
+    match operation {
+        MaintenanceOperation::Analyze | MaintenanceOperation::RefreshMaterializedView => {
+            execute_in_transaction(conn, sql, timeouts).await
+        }
+        MaintenanceOperation::Vacuum | MaintenanceOperation::Reindex => {
+            execute_in_session(conn, sql, timeouts).await
+        }
+    }
+}
+
+/// Executes the statement on a connection from the pool.
+///
+/// The connection is closed instead of being returned if its settings could not be reset.
+pub async fn execute_pooled(
+    p: &PgPool,
+    operation: MaintenanceOperation,
+    sql: &str,
+    timeouts: &Timeouts,
+) -> Result<(), MaintenanceError> {
+    let mut conn = p.acquire().await?;
+    let res: Result<(), MaintenanceError> =
+        execute_with_timeouts(&mut conn, operation, sql, timeouts).await;
+    if res.is_err() && !timeouts.is_empty() {
+        conn.close_on_drop();
+    }
+    res
+}
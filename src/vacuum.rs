@@ -0,0 +1,317 @@
+use std::io;
+
+use sqlx::PgPool;
+
+use async_graphql::Enum;
+use async_graphql::InputObject;
+
+use crate::CheckedTableName;
+use crate::PG_VERSION_SKIP_LOCKED;
+use crate::ServerVersion;
+use crate::error::MaintenanceError;
+use crate::relation::RelKind;
+
+pub const PG_VERSION_INDEX_CLEANUP: i32 = 120000;
+pub const PG_VERSION_TRUNCATE: i32 = 120000;
+pub const PG_VERSION_PARALLEL: i32 = 130000;
+pub const PG_VERSION_PROCESS_TOAST: i32 = 140000;
+pub const PG_VERSION_INDEX_CLEANUP_AUTO: i32 = 140000;
+
+pub const PARALLEL_WORKERS_MAX: i32 = 1024;
+
+#[derive(Clone, Copy, PartialEq, Eq, Enum)]
+pub enum IndexCleanup {
+    /// Requires PostgreSQL 14 or later.
+    Auto,
+    On,
+    Off,
+}
+
+impl IndexCleanup {
+    pub fn as_sql(&self) -> &'static str {
+        match self {
+            Self::Auto => "AUTO",
+            Self::On => "ON",
+            Self::Off => "OFF",
+        }
+    }
+}
+
+#[derive(Clone, Default, InputObject)]
+pub struct VacuumOptions {
+    pub full: Option<bool>,
+    pub freeze: Option<bool>,
+    pub analyze: Option<bool>,
+    pub disable_page_skipping: Option<bool>,
+
+    /// Requires PostgreSQL 12 or later.
+    pub index_cleanup: Option<IndexCleanup>,
+
+    /// Requires PostgreSQL 14 or later.
+    pub process_toast: Option<bool>,
+
+    /// Requires PostgreSQL 12 or later.
+    pub truncate: Option<bool>,
+
+    /// The number of parallel workers(0 disables). Requires PostgreSQL 13 or later.
+    pub parallel: Option<i32>,
+
+    /// Requires PostgreSQL 12 or later.
+    pub skip_locked: Option<bool>,
+}
+
+fn require_version(option: &str, required: i32, server_version_num: i32) -> Result<(), io::Error> {
+    match required <= server_version_num {
+        true => Ok(()),
+        false => Err(MaintenanceError::Unsupported(format!(
+            "{option} not supported: server version {server_version_num}"
+        ))
+        .into()),
+    }
+}
+
+fn bool2sql(b: bool) -> &'static str {
+    match b {
+        true => "TRUE",
+        false => "FALSE",
+    }
+}
+
+impl VacuumOptions {
+    /// Creates the option list(e.g, "(FULL, ANALYZE)") for the server.
+    pub fn to_sql(&self, server_version_num: i32) -> Result<String, io::Error> {
+        let mut opts: Vec<String> = vec![];
+
+        let flags = [
+            ("FULL", self.full),
+            ("FREEZE", self.freeze),
+            ("ANALYZE", self.analyze),
+            ("DISABLE_PAGE_SKIPPING", self.disable_page_skipping),
+        ];
+        for (name, flag) in flags {
+            if flag.unwrap_or_default() {
+                opts.push(name.into());
+            }
+        }
+
+        if let Some(cleanup) = self.index_cleanup {
+            require_version(
+                "INDEX_CLEANUP",
+                PG_VERSION_INDEX_CLEANUP,
+                server_version_num,
+            )?;
+            if IndexCleanup::Auto == cleanup {
+                require_version(
+                    "INDEX_CLEANUP AUTO",
+                    PG_VERSION_INDEX_CLEANUP_AUTO,
+                    server_version_num,
+                )?;
+            }
+            opts.push(format!("INDEX_CLEANUP {}", cleanup.as_sql()));
+        }
+
+        if let Some(b) = self.process_toast {
+            require_version(
+                "PROCESS_TOAST",
+                PG_VERSION_PROCESS_TOAST,
+                server_version_num,
+            )?;
+            opts.push(format!("PROCESS_TOAST {}", bool2sql(b)));
+        }
+
+        if let Some(b) = self.truncate {
+            require_version("TRUNCATE", PG_VERSION_TRUNCATE, server_version_num)?;
+            opts.push(format!("TRUNCATE {}", bool2sql(b)));
+        }
+
+        if let Some(workers) = self.parallel {
+            require_version("PARALLEL", PG_VERSION_PARALLEL, server_version_num)?;
+            if !(0..=PARALLEL_WORKERS_MAX).contains(&workers) {
+                return Err(MaintenanceError::InvalidInput(format!(
+                    "parallel workers out of range: {workers}"
+                ))
+                .into());
+            }
+            if self.full.unwrap_or_default() {
+                return Err(MaintenanceError::InvalidInput(
+                    "PARALLEL can not be used with FULL".into(),
+                )
+                .into());
+            }
+            opts.push(format!("PARALLEL {workers}"));
+        }
+
+        if self.skip_locked.unwrap_or_default() {
+            require_version("SKIP_LOCKED", PG_VERSION_SKIP_LOCKED, server_version_num)?;
+            opts.push("SKIP_LOCKED".into());
+        }
+
+        if opts.is_empty() {
+            return Ok("".into());
+        }
+        Ok(format!("({})", opts.join(", ")))
+    }
+}
+
+pub struct PgVacuum {
+    pub pool: PgPool,
+    version: ServerVersion,
+}
+
+impl PgVacuum {
+    pub fn new(p: &PgPool) -> Self {
+        Self {
+            pool: p.clone(),
+            version: ServerVersion::default(),
+        }
+    }
+
+    pub async fn vacuum_sql(
+        &self,
+        table: &CheckedTableName,
+        opts: &VacuumOptions,
+    ) -> Result<String, io::Error> {
+        if RelKind::ForeignTable == table.kind() {
+            return Err(MaintenanceError::InvalidInput(format!(
+                "the table {} is a foreign table which can not be vacuumed",
+                table.as_str(),
+            ))
+            .into());
+        }
+        let ver: i32 = self.version.get(&self.pool).await?;
+        let opt_sql: String = opts.to_sql(ver)?;
+        Ok(crate::join_sql(&["VACUUM", &opt_sql, &table.qualified()]))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn unsupported(opts: &VacuumOptions, ver: i32) -> bool {
+        opts.to_sql(ver)
+            .is_err_and(|e| MaintenanceError::from_io_ref(&e).code() == "UNSUPPORTED")
+    }
+
+    fn invalid(opts: &VacuumOptions, ver: i32) -> bool {
+        opts.to_sql(ver)
+            .is_err_and(|e| MaintenanceError::from_io_ref(&e).code() == "INVALID_INPUT")
+    }
+
+    #[test]
+    fn version_gates() {
+        let cases: [(VacuumOptions, i32); 6] = [
+            (
+                VacuumOptions {
+                    index_cleanup: Some(IndexCleanup::On),
+                    ..Default::default()
+                },
+                PG_VERSION_INDEX_CLEANUP,
+            ),
+            (
+                VacuumOptions {
+                    index_cleanup: Some(IndexCleanup::Auto),
+                    ..Default::default()
+                },
+                PG_VERSION_INDEX_CLEANUP_AUTO,
+            ),
+            (
+                VacuumOptions {
+                    process_toast: Some(false),
+                    ..Default::default()
+                },
+                PG_VERSION_PROCESS_TOAST,
+            ),
+            (
+                VacuumOptions {
+                    truncate: Some(false),
+                    ..Default::default()
+                },
+                PG_VERSION_TRUNCATE,
+            ),
+            (
+                VacuumOptions {
+                    parallel: Some(2),
+                    ..Default::default()
+                },
+                PG_VERSION_PARALLEL,
+            ),
+            (
+                VacuumOptions {
+                    skip_locked: Some(true),
+                    ..Default::default()
+                },
+                PG_VERSION_SKIP_LOCKED,
+            ),
+        ];
+        for (opts, required) in cases {
+            assert!(unsupported(&opts, required - 1), "{required}");
+            assert!(opts.to_sql(required).is_ok(), "{required}");
+        }
+    }
+
+    #[test]
+    fn unset_options_need_no_version() {
+        let opts = VacuumOptions {
+            full: Some(true),
+            skip_locked: Some(false),
+            ..Default::default()
+        };
+        assert_eq!(opts.to_sql(110000).expect("sql"), "(FULL)");
+    }
+
+    #[test]
+    fn parallel_out_of_range() {
+        for workers in [-1, PARALLEL_WORKERS_MAX + 1, i32::MIN, i32::MAX] {
+            let opts = VacuumOptions {
+                parallel: Some(workers),
+                ..Default::default()
+            };
+            assert!(invalid(&opts, 160000), "{workers}");
+        }
+        for workers in [0, PARALLEL_WORKERS_MAX] {
+            let opts = VacuumOptions {
+                parallel: Some(workers),
+                ..Default::default()
+            };
+            let expected: String = format!("(PARALLEL {workers})");
+            assert_eq!(opts.to_sql(160000).expect("sql"), expected);
+        }
+    }
+
+    #[test]
+    fn parallel_with_full() {
+        let opts = VacuumOptions {
+            full: Some(true),
+            parallel: Some(2),
+            ..Default::default()
+        };
+        assert!(invalid(&opts, 160000));
+        let not_full = VacuumOptions {
+            full: Some(false),
+            ..opts
+        };
+        assert_eq!(not_full.to_sql(160000).expect("sql"), "(PARALLEL 2)");
+    }
+
+    #[test]
+    fn option_list() {
+        assert_eq!(VacuumOptions::default().to_sql(160000).expect("sql"), "");
+        let all = VacuumOptions {
+            full: Some(false),
+            freeze: Some(true),
+            analyze: Some(true),
+            disable_page_skipping: Some(true),
+            index_cleanup: Some(IndexCleanup::Off),
+            process_toast: Some(true),
+            truncate: Some(false),
+            parallel: Some(4),
+            skip_locked: Some(true),
+        };
+        assert_eq!(
+            all.to_sql(160000).expect("sql"),
+            "(FREEZE, ANALYZE, DISABLE_PAGE_SKIPPING, INDEX_CLEANUP OFF, \
+             PROCESS_TOAST TRUE, TRUNCATE FALSE, PARALLEL 4, SKIP_LOCKED)",
+        );
+    }
+}
@@ -0,0 +1,274 @@
+use std::io;
+use std::time::Duration;
+
+use chrono::DateTime;
+use chrono::Days;
+use chrono::NaiveTime;
+use chrono::TimeZone;
+use chrono::Utc;
+
+use chrono_tz::Tz;
+
+use async_graphql::Enum;
+use async_graphql::SimpleObject;
+
+use crate::batch::MaintenanceOperation;
+use crate::error::MaintenanceError;
+
+/// The interval to check if a window of a queued job opened.
+pub const WINDOW_POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+/// A daily time range in which the operations are allowed.
+#[derive(Clone)]
+pub struct MaintenanceWindow {
+    /// The operations restricted to the window(all if empty).
+    pub operations: Vec<MaintenanceOperation>,
+    pub timezone: Tz,
+    pub start: NaiveTime,
+
+    /// The end(exclusive). The window spans midnight if end is not after start.
+    pub end: NaiveTime,
+}
+
+impl MaintenanceWindow {
+    /// Parses the window(e.g, "22:00-06:00 Asia/Tokyo", "01:30-04:00"(UTC)).
+    pub fn parse(operations: Vec<MaintenanceOperation>, spec: &str) -> Result<Self, io::Error> {
+        let invalid = || -> io::Error {
+            MaintenanceError::InvalidInput(format!("invalid maintenance window: {spec}")).into()
+        };
+        let (range, tz) = spec.trim().split_once(' ').unwrap_or((spec.trim(), "UTC"));
+        let (start, end) = range.split_once('-').ok_or_else(invalid)?;
+        let parse_time = |t: &str| NaiveTime::parse_from_str(t, "%H:%M").map_err(|_| invalid());
+        Ok(Self {
+            operations,
+            timezone: tz.trim().parse().map_err(|_| invalid())?,
+            start: parse_time(start)?,
+            end: parse_time(end)?,
+        })
+    }
+
+    pub fn applies_to(&self, operation: MaintenanceOperation) -> bool {
+        self.operations.is_empty() || self.operations.contains(&operation)
+    }
+
+    pub fn contains(&self, now: DateTime<Utc>) -> bool {
+        let t: NaiveTime = now.with_timezone(&self.timezone).time();
+        match self.start < self.end {
+            true => self.start <= t && t < self.end,
+            false => self.start <= t || t < self.end,
+        }
+    }
+
+    /// Gets the first occurrence of the local time after now(days skipped by DST included).
+    fn next_after(&self, time: NaiveTime, now: DateTime<Utc>) -> Option<DateTime<Utc>> {
+        let today = now.with_timezone(&self.timezone).date_naive();
+        (0..3)
+            .filter_map(|d| today.checked_add_days(Days::new(d)))
+            .filter_map(|day| {
+                self.timezone
+                    .from_local_datetime(&day.and_time(time))
+                    .earliest()
+            })
+            .map(|local| local.with_timezone(&Utc))
+            .find(|at| now < *at)
+    }
+
+    /// Gets the next start(now if open).
+    pub fn next_open(&self, now: DateTime<Utc>) -> Option<DateTime<Utc>> {
+        match self.contains(now) {
+            true => Some(now),
+            false => self.next_after(self.start, now),
+        }
+    }
+
+    /// Gets the end of the current window(None if closed).
+    pub fn closes_at(&self, now: DateTime<Utc>) -> Option<DateTime<Utc>> {
+        match self.contains(now) {
+            true => self.next_after(self.end, now),
+            false => None,
+        }
+    }
+}
+
+/// How requests outside the window are handled.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Enum)]
+pub enum OutsideWindow {
+    /// Rejects the mutations and the job submissions.
+    #[default]
+    Reject,
+
+    /// Rejects the mutations but keeps the jobs queued until the window opens.
+    Queue,
+}
+
+#[derive(SimpleObject)]
+pub struct WindowState {
+    pub operation: MaintenanceOperation,
+
+    /// true if any window restricts the operation.
+    pub restricted: bool,
+    pub open: bool,
+    pub closes_at: Option<DateTime<Utc>>,
+    pub next_open: Option<DateTime<Utc>>,
+}
+
+/// The windows of the operations(unrestricted if no window applies).
+#[derive(Clone, Default)]
+pub struct WindowPolicy {
+    pub windows: Vec<MaintenanceWindow>,
+    pub outside: OutsideWindow,
+}
+
+impl WindowPolicy {
+    fn applicable(
+        &self,
+        operation: MaintenanceOperation,
+    ) -> impl Iterator<Item = &MaintenanceWindow> {
+        self.windows.iter().filter(move |w| w.applies_to(operation))
+    }
+
+    pub fn is_restricted(&self, operation: MaintenanceOperation) -> bool {
+        self.applicable(operation).next().is_some()
+    }
+
+    pub fn is_open(&self, operation: MaintenanceOperation, now: DateTime<Utc>) -> bool {
+        !self.is_restricted(operation) || self.applicable(operation).any(|w| w.contains(now))
+    }
+
+    pub fn state(&self, operation: MaintenanceOperation, now: DateTime<Utc>) -> WindowState {
+        let restricted: bool = self.is_restricted(operation);
+        WindowState {
+            operation,
+            restricted,
+            open: self.is_open(operation, now),
+            closes_at: self
+                .applicable(operation)
+                .filter_map(|w| w.closes_at(now))
+                .max(),
+            next_open: match restricted {
+                true => self
+                    .applicable(operation)
+                    .filter_map(|w| w.next_open(now))
+                    .min(),
+                false => Some(now),
+            },
+        }
+    }
+
+    /// Rejects the operation outside its windows.
+    pub fn check(&self, operation: MaintenanceOperation) -> Result<(), io::Error> {
+        let now: DateTime<Utc> = Utc::now();
+        if self.is_open(operation, now) {
+            return Ok(());
+        }
+        let next: String = self
+            .state(operation, now)
+            .next_open
+            .map(|at| at.to_rfc3339())
+            .unwrap_or_else(|| "unknown".into());
+        Err(MaintenanceError::OutsideWindow(format!(
+            "{} is not allowed outside the maintenance window(next: {next})",
+            operation.as_str(),
+        ))
+        .into())
+    }
+
+    /// Rejects the job unless it can be queued until the window opens.
+    pub fn check_submit(&self, operation: MaintenanceOperation) -> Result<(), io::Error> {
+        match self.outside {
+            OutsideWindow::Reject => self.check(operation),
+            OutsideWindow::Queue => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(rfc3339: &str) -> DateTime<Utc> {
+        DateTime::parse_from_rfc3339(rfc3339)
+            .map(|t| t.with_timezone(&Utc))
+            .expect("valid time")
+    }
+
+    fn window(spec: &str) -> MaintenanceWindow {
+        MaintenanceWindow::parse(vec![], spec).expect("valid window")
+    }
+
+    #[test]
+    fn parse_defaults_to_utc() {
+        let w: MaintenanceWindow = window("01:30-04:00");
+        assert_eq!(w.timezone, Tz::UTC);
+        assert_eq!(w.start, NaiveTime::from_hms_opt(1, 30, 0).expect("time"));
+        assert_eq!(w.end, NaiveTime::from_hms_opt(4, 0, 0).expect("time"));
+    }
+
+    #[test]
+    fn parse_rejects_invalid_specs() {
+        for spec in [
+            "",
+            "01:30",
+            "01:30-25:00",
+            "1am-4am",
+            "01:30-04:00 Mars/Base",
+        ] {
+            let e: io::Error = MaintenanceWindow::parse(vec![], spec).err().expect(spec);
+            assert_eq!(e.kind(), io::ErrorKind::InvalidInput, "{spec}");
+        }
+    }
+
+    #[test]
+    fn contains_excludes_end() {
+        let w: MaintenanceWindow = window("01:30-04:00");
+        assert!(!w.contains(at("2026-01-01T01:29:59Z")));
+        assert!(w.contains(at("2026-01-01T01:30:00Z")));
+        assert!(w.contains(at("2026-01-01T03:59:59Z")));
+        assert!(!w.contains(at("2026-01-01T04:00:00Z")));
+    }
+
+    #[test]
+    fn contains_spanning_midnight() {
+        let w: MaintenanceWindow = window("22:00-06:00 Asia/Tokyo");
+        // 22:00 and 06:00 in Tokyo(UTC+9)
+        assert!(!w.contains(at("2026-01-01T12:59:59Z")));
+        assert!(w.contains(at("2026-01-01T13:00:00Z")));
+        assert!(w.contains(at("2026-01-01T15:00:00Z")));
+        assert!(w.contains(at("2026-01-01T20:59:59Z")));
+        assert!(!w.contains(at("2026-01-01T21:00:00Z")));
+    }
+
+    #[test]
+    fn contains_all_day_if_start_equals_end() {
+        let w: MaintenanceWindow = window("03:00-03:00");
+        for t in ["02:59:59", "03:00:00", "12:00:00", "23:59:59", "00:00:00"] {
+            assert!(w.contains(at(&format!("2026-01-01T{t}Z"))), "{t}");
+        }
+    }
+
+    #[test]
+    fn next_open_and_closes_at() {
+        let w: MaintenanceWindow = window("22:00-06:00");
+        let closed: DateTime<Utc> = at("2026-01-01T12:00:00Z");
+        assert_eq!(w.next_open(closed), Some(at("2026-01-01T22:00:00Z")));
+        assert_eq!(w.closes_at(closed), None);
+
+        let open: DateTime<Utc> = at("2026-01-01T23:00:00Z");
+        assert_eq!(w.next_open(open), Some(open));
+        assert_eq!(w.closes_at(open), Some(at("2026-01-02T06:00:00Z")));
+    }
+
+    #[test]
+    fn policy_unrestricted_without_windows() {
+        let policy = WindowPolicy {
+            windows: vec![
+                MaintenanceWindow::parse(vec![MaintenanceOperation::Vacuum], "01:00-02:00")
+                    .expect("valid window"),
+            ],
+            ..Default::default()
+        };
+        let now: DateTime<Utc> = at("2026-01-01T12:00:00Z");
+        assert!(policy.is_open(MaintenanceOperation::Analyze, now));
+        assert!(!policy.is_open(MaintenanceOperation::Vacuum, now));
+    }
+}